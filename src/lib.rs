@@ -1 +1,6 @@
 pub mod transpiler;
+
+pub use transpiler::{
+    transpile, transpile_output, transpile_to, transpile_with_passes, Error, Options, Output, Pass,
+    SourceFormat, Target,
+};
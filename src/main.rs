@@ -1,34 +1,533 @@
-use std::{fs, path::PathBuf, process};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process,
+};
 
-use clap::{arg, command, Parser};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
-use saurus::transpiler;
+use saurus::transpiler::{
+    self, check,
+    code_blocks::CodeBackend,
+    config::{self, Config},
+    diagnostics::Diagnostic,
+    filter::Filter,
+    highlight,
+    lexer::Lexer,
+    markdown, pandoc, parser, styles, Pass, SourceFormat, Target,
+};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum To {
+    Latex,
+    Typst,
+    Html,
+}
+
+impl From<To> for Target {
+    fn from(to: To) -> Self {
+        match to {
+            To::Latex => Target::Latex,
+            To::Typst => Target::Typst,
+            To::Html => Target::Html,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Code {
+    Listings,
+    Minted,
+    Verbatim,
+    Fancyvrb,
+    Builtin,
+}
+
+impl From<Code> for CodeBackend {
+    fn from(code: Code) -> Self {
+        match code {
+            Code::Listings => CodeBackend::Listings,
+            Code::Minted => CodeBackend::Minted,
+            Code::Verbatim => CodeBackend::Verbatim,
+            Code::Fancyvrb => CodeBackend::Fancyvrb,
+            Code::Builtin => CodeBackend::Builtin,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Source {
+    Markdown,
+    PandocJson,
+}
+
+impl From<Source> for SourceFormat {
+    fn from(source: Source) -> Self {
+        match source {
+            Source::Markdown => SourceFormat::Markdown,
+            Source::PandocJson => SourceFormat::PandocJson,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// The lexer's token for each line
+    Tokens,
+    /// The parser's records, marking where each group starts and ends
+    Parse,
+    /// The document tree
+    Tree,
+    /// The generated document, as written by default
+    Tex,
+    /// The document tree as pandoc's JSON AST
+    PandocJson,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Each problem with the offending line, like a compiler
+    Text,
+    /// A JSON list of the problems in every file
+    Json,
+}
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
-    input: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    transpile: Transpile,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert LaTeX back into Markdown
+    Import {
+        input: PathBuf,
+        /// Defaults to the input with a `.md` extension, or stdout when reading stdin
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Rewrite Markdown into the canonical form saurus parses best
+    Fmt {
+        /// The files to format in place, or `-` to format stdin to stdout
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Only report the files that are not formatted, failing if there are any
+        #[arg(long)]
+        check: bool,
+    },
+    /// Report Markdown that saurus does not support or that is likely to break
+    Check {
+        /// The files to check, or `-` for stdin
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// How to report the problems found
+        #[arg(long, value_enum, default_value = "text")]
+        format: Format,
+        /// Read settings from this file instead of a `saurus.json` next to each file
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+struct Transpile {
+    /// The document to read, or `-` for stdin
+    #[arg(required = true)]
+    input: Option<String>,
+    /// Where to write the result, or `-` for stdout
     #[arg(short, long)]
     output: Option<String>,
+    /// Input format
+    #[arg(long, value_enum, default_value = "markdown")]
+    from: Source,
+    /// Output format
+    #[arg(long, value_enum, default_value = "latex")]
+    to: To,
+    /// Pipe the document as pandoc JSON through this program before generating output
+    #[arg(long, value_name = "PROGRAM")]
+    filter: Vec<PathBuf>,
+    /// Write the document in this form instead of the output format
+    #[arg(long, value_enum)]
+    emit: Option<Emit>,
+    /// Write what `--emit` selects as JSON
+    #[arg(long, requires = "emit")]
+    json: bool,
+    /// Precede the TeX generated from each Markdown line with a `% md:LINE` comment
+    #[arg(long)]
+    line_comments: bool,
+    /// Read settings from this file instead of a `saurus.json` next to the input
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Fail on any warning, including lines saurus ignores
+    #[arg(long)]
+    strict: bool,
+    /// The LaTeX package to write code blocks for, instead of the configured one
+    #[arg(long, value_enum, value_name = "PACKAGE")]
+    code_backend: Option<Code>,
+    /// The theme the builtin code backend colors code with, instead of the configured one
+    #[arg(long, value_name = "THEME")]
+    code_theme: Option<String>,
+    /// The style listings are written in unless they choose another, instead of the configured one
+    #[arg(long, value_name = "STYLE")]
+    listing_style: Option<String>,
+    /// Write the lines of files listed by code blocks into the document instead of referring to them
+    #[arg(long)]
+    inline_files: bool,
+    /// Write a JSON map between TeX and Markdown lines to this file
+    #[arg(long, value_name = "FILE")]
+    line_map: Option<PathBuf>,
+    /// Print this LaTeX log with its locations translated to Markdown lines
+    #[arg(long, value_name = "LOG")]
+    translate_log: Option<PathBuf>,
 }
 
 fn main() {
     let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Import { input, output }) => import(input, output),
+        Some(Command::Fmt { files, check }) => fmt(files, check),
+        Some(Command::Check {
+            files,
+            format,
+            config,
+        }) => lint(files, format, config),
+        None => transpile(cli.transpile),
+    }
+}
 
-    let input = PathBuf::from(cli.input);
-    if !input.exists() {
+fn transpile(cli: Transpile) {
+    let input = PathBuf::from(cli.input.unwrap_or_default());
+    let stdin = input == Path::new("-");
+    let target = Target::from(cli.to);
+    if !stdin && !input.exists() {
         eprintln!("{:?} does not exist or file permissions deny use!", input);
         process::exit(1);
     }
 
-    let output = if let Some(output) = cli.output {
-        PathBuf::from(output)
+    // Debugging output goes to stdout unless asked otherwise
+    let extension = match (cli.emit, cli.json) {
+        (Some(Emit::PandocJson), _) => Some("json"),
+        (None, _) | (Some(Emit::Tex), false) => Some(target.extension()),
+        _ => None,
+    };
+    let output = match (cli.output, extension) {
+        (Some(output), _) => PathBuf::from(output),
+        (None, Some(extension)) if !stdin => input.with_extension(extension),
+        _ => PathBuf::from("-"),
+    };
+
+    let mut options = transpiler::Options {
+        from: SourceFormat::from(cli.from),
+        target,
+        line_comments: cli.line_comments,
+        strict: cli.strict,
+        base_dir: base_dir(&input),
+        inline_files: cli.inline_files,
+        output_dir: output_dir(&output),
+        ..transpiler::Options::default()
+    };
+    load_config(&input, cli.config.as_deref()).apply(&mut options);
+    if let Some(code) = cli.code_backend {
+        options.code_backend = CodeBackend::from(code);
+    }
+    if let Some(theme) = cli.code_theme {
+        if !highlight::is_theme(&theme) {
+            let themes: Vec<_> = highlight::theme_names().collect();
+            eprintln!(
+                "Unknown code theme {:?}, the themes are {}",
+                theme,
+                themes.join(", ")
+            );
+            process::exit(1);
+        }
+        options.code_theme = Some(theme);
+    }
+    if let Some(style) = cli.listing_style {
+        if styles::find(&style, &options.listing_styles).is_none() {
+            let mut known: Vec<&str> = styles::BUILTIN.to_vec();
+            known.extend(options.listing_styles.keys().map(String::as_str));
+            eprintln!(
+                "Unknown listing style {:?}, the styles are {}",
+                style,
+                known.join(", ")
+            );
+            process::exit(1);
+        }
+        options.listing_style = Some(style);
+    }
+
+    let file_str = read_input(&input);
+    let file_name = if stdin {
+        "<stdin>".to_string()
     } else {
-        let mut file = input.clone();
-        file.set_extension("tex");
-        file
+        input.display().to_string()
     };
+    let mut passes: Vec<Box<dyn Pass>> = cli
+        .filter
+        .into_iter()
+        .map(|program| Box::new(Filter::new(program, target)) as Box<dyn Pass>)
+        .collect();
+    match cli.emit {
+        Some(Emit::Tokens | Emit::Parse) if options.from != SourceFormat::Markdown => {
+            eprintln!("Only Markdown is read as tokens and records");
+            process::exit(1);
+        }
+        Some(Emit::Tokens) => {
+            let mut lexer = Lexer::new();
+            lexer.tokenize(&file_str);
+            let text = if cli.json {
+                to_json(&lexer.results)
+            } else {
+                lines(&lexer.results)
+            };
+            write_output(&output, &text);
+            return;
+        }
+        Some(Emit::Parse) => {
+            let mut lexer = Lexer::new();
+            lexer.tokenize(&file_str);
+            let mut parser = parser::Parser::new();
+            parser.run(lexer);
+            let text = if cli.json {
+                to_json(&parser.results)
+            } else {
+                lines(&parser.results)
+            };
+            write_output(&output, &text);
+            return;
+        }
+        _ => {}
+    }
+    if let Some(emit @ (Emit::Tree | Emit::PandocJson)) = cli.emit {
+        let mut diagnostics = Vec::new();
+        let document =
+            transpiler::read_with_passes(&file_str, &options, &mut passes, &mut diagnostics)
+                .unwrap_or_else(|error| fail(error, &file_str, &file_name));
+        report(&diagnostics, &file_str, &file_name);
+        let text = match emit {
+            Emit::Tree if cli.json => to_json(&document),
+            Emit::Tree => format!("{:#?}\n", document),
+            _ => pandoc::to_json(&document),
+        };
+        write_output(&output, &text);
+        return;
+    }
 
-    let file_str = fs::read_to_string(input).expect("Unable to read from file!");
-    transpiler::run(&file_str, &output);
+    let result = transpiler::transpile_with_passes(&file_str, &options, &mut passes)
+        .unwrap_or_else(|error| fail(error, &file_str, &file_name));
+    report(&result.diagnostics, &file_str, &file_name);
+    if cli.json {
+        let json = serde_json::json!({
+            "tex": result.tex,
+            "diagnostics": result.diagnostics,
+            "source_map": result.source_map,
+        });
+        write_output(&output, &format!("{:#}\n", json));
+    } else {
+        write_output(&output, &result.tex);
+    }
+    let tex_name = output
+        .file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().to_string());
+    if let Some(line_map) = cli.line_map {
+        let json = result.source_map.to_json(&file_name, &tex_name);
+        if let Err(error) = fs::write(&line_map, json) {
+            eprintln!("Unable to write {:?}: {}", line_map, error);
+            process::exit(1);
+        }
+    }
+    if let Some(log) = cli.translate_log {
+        match fs::read_to_string(&log) {
+            Ok(log) => print!(
+                "{}",
+                result.source_map.translate_log(&log, &tex_name, &file_name)
+            ),
+            Err(error) => {
+                eprintln!("Unable to read {:?}: {}", log, error);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+fn import(input: PathBuf, output: Option<PathBuf>) {
+    let tex = read_input(&input);
+    let stdin = input == Path::new("-");
+    let output = match output {
+        Some(output) => output,
+        None if stdin => PathBuf::from("-"),
+        None => input.with_extension("md"),
+    };
+    let file_name = if stdin {
+        "<stdin>".to_string()
+    } else {
+        input.display().to_string()
+    };
+
+    let mut diagnostics = Vec::new();
+    let markdown = transpiler::import(&tex, &mut diagnostics);
+    report(&diagnostics, &tex, &file_name);
+    write_output(&output, &markdown);
+}
+
+fn fmt(files: Vec<PathBuf>, check: bool) {
+    let mut failed = false;
+    for file in files {
+        let source = read_input(&file);
+        let mut diagnostics = Vec::new();
+        let Some(formatted) = markdown::format(&source, &mut diagnostics) else {
+            report(&diagnostics, &source, &file.display().to_string());
+            failed = true;
+            continue;
+        };
+        if check {
+            if formatted != source {
+                eprintln!("{} is not formatted", file.display());
+                failed = true;
+            }
+        } else if file == Path::new("-") || formatted != source {
+            write_output(&file, &formatted);
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn lint(files: Vec<PathBuf>, format: Format, config: Option<PathBuf>) {
+    let mut found = Vec::new();
+    for file in files {
+        let source = read_input(&file);
+        let mut options = transpiler::Options {
+            base_dir: base_dir(&file),
+            ..transpiler::Options::default()
+        };
+        load_config(&file, config.as_deref()).apply(&mut options);
+        let file_name = if file == Path::new("-") {
+            "<stdin>".to_string()
+        } else {
+            file.display().to_string()
+        };
+        let diagnostics = check::check(&source, &options);
+        if let Format::Text = format {
+            report(&diagnostics, &source, &file_name);
+        }
+        found.extend(
+            diagnostics
+                .into_iter()
+                .map(|diagnostic| (file_name.clone(), diagnostic)),
+        );
+    }
+    if let Format::Json = format {
+        let json: Vec<_> = found
+            .iter()
+            .map(|(file, diagnostic)| {
+                serde_json::json!({
+                    "file": file,
+                    "severity": diagnostic.severity,
+                    "line": diagnostic.line,
+                    "column": diagnostic.column,
+                    "message": diagnostic.message,
+                    "hint": diagnostic.hint,
+                })
+            })
+            .collect();
+        print!("{}", to_json(&json));
+    }
+    if !found.is_empty() {
+        process::exit(1);
+    }
+}
+
+/// The directory `input` is in, or the current one for stdin
+fn base_dir(input: &Path) -> PathBuf {
+    match input.parent() {
+        Some(parent) if input != Path::new("-") => parent.to_path_buf(),
+        _ => PathBuf::new(),
+    }
+}
+
+/// The directory `output` is written to, or `None` for stdout
+fn output_dir(output: &Path) -> Option<PathBuf> {
+    match output.parent() {
+        _ if output == Path::new("-") => None,
+        Some(parent) if parent != Path::new("") => Some(parent.to_path_buf()),
+        _ => Some(PathBuf::from(".")),
+    }
+}
+
+/// The configuration at `path`, or else the one next to `input` if there is one
+fn load_config(input: &Path, path: Option<&Path>) -> Config {
+    let found;
+    let path = match path {
+        Some(path) => path,
+        None => {
+            found = base_dir(input).join(config::FILE_NAME);
+            if !found.exists() {
+                return Config::default();
+            }
+            &found
+        }
+    };
+    Config::load(path).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    })
+}
+
+/// Each item's readable form on a line of its own
+fn lines<T: std::fmt::Display>(items: &[T]) -> String {
+    items.iter().map(|item| format!("{}\n", item)).collect()
+}
+
+fn to_json<T: serde::Serialize + ?Sized>(value: &T) -> String {
+    let json = serde_json::to_string_pretty(value).expect("intermediate state serializes");
+    format!("{}\n", json)
+}
+
+/// Reads the file at `path`, or stdin for `-`
+fn read_input(path: &Path) -> String {
+    let result = if path == Path::new("-") {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text).map(|_| text)
+    } else {
+        fs::read_to_string(path)
+    };
+    result.unwrap_or_else(|error| {
+        eprintln!("Unable to read {:?}: {}", path, error);
+        process::exit(1);
+    })
+}
+
+/// Writes `text` to the file at `path`, or stdout for `-`
+fn write_output(path: &Path, text: &str) {
+    let result = if path == Path::new("-") {
+        io::stdout().write_all(text.as_bytes())
+    } else {
+        fs::write(path, text)
+    };
+    if let Err(error) = result {
+        eprintln!("Unable to write {:?}: {}", path, error);
+        process::exit(1);
+    }
+}
+
+fn fail(error: transpiler::Error, source: &str, file_name: &str) -> ! {
+    match error {
+        transpiler::Error::Diagnostics(diagnostics) => report(&diagnostics, source, file_name),
+        error => eprintln!("{}", error),
+    }
+    process::exit(1);
+}
+
+fn report(diagnostics: &[Diagnostic], source: &str, file_name: &str) {
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic.render(source, file_name));
+    }
 }
@@ -1,33 +1,450 @@
-use std::fs;
-use std::io::{Error, Write};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::mem;
 use std::path::PathBuf;
 
+use ast::Document;
+use backend::{Backend, Context, Fragment, Latex};
+use code_blocks::{CodeBackend, Languages};
+use diagnostics::{Diagnostic, Severity};
+use html::Html;
+use lexer::Span;
+use source_map::SourceMap;
+use styles::ListingStyle;
+use typst::Typst;
+
+pub mod ast;
+pub mod backend;
+pub mod check;
 pub mod code_blocks;
+pub mod config;
+pub mod diagnostics;
+pub mod filter;
 pub mod generator;
+pub mod highlight;
+pub mod html;
+pub mod include;
+pub mod inline;
+pub mod latex;
 pub mod lexer;
+pub mod markdown;
+pub mod pandoc;
 pub mod parser;
 pub mod re;
+pub mod source_map;
+pub mod styles;
+pub mod typst;
+pub mod visit;
+
+/// The format a document is generated in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Target {
+    #[default]
+    Latex,
+    Typst,
+    /// A standalone page with embedded styles
+    Html,
+}
+
+impl Target {
+    /// The file extension of documents in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Target::Latex => "tex",
+            Target::Typst => "typ",
+            Target::Html => "html",
+        }
+    }
+
+    /// The name of this format, as pandoc knows it
+    pub fn name(&self) -> &'static str {
+        match self {
+            Target::Latex => "latex",
+            Target::Typst => "typst",
+            Target::Html => "html",
+        }
+    }
+}
+
+/// The format a document is read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SourceFormat {
+    #[default]
+    Markdown,
+    /// Pandoc's JSON AST, as written by [`pandoc::to_json`] or `pandoc -t json`
+    PandocJson,
+}
+
+/// Settings that control how a document is transpiled.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub from: SourceFormat,
+    pub target: Target,
+    /// Precede the TeX generated from each Markdown line with a `% md:LINE` comment
+    pub line_comments: bool,
+    /// Fail on any warning, including lines that are ignored
+    pub strict: bool,
+    /// How code block languages become listings languages
+    pub languages: Languages,
+    /// The LaTeX package code blocks are written for
+    pub code_backend: CodeBackend,
+    /// The bundled theme the `builtin` code backend colors code with,
+    /// [`highlight::DEFAULT_THEME`] if not set
+    pub code_theme: Option<String>,
+    /// The style listings are written in unless they choose another,
+    /// [`styles::DEFAULT_STYLE`] if not set
+    pub listing_style: Option<String>,
+    /// Listing styles to define besides the built-in ones
+    pub listing_styles: HashMap<String, ListingStyle>,
+    /// The directory files listed by code blocks are relative to, usually the Markdown file's
+    pub base_dir: PathBuf,
+    /// Write the lines of files listed by code blocks into the document instead of referring to them
+    pub inline_files: bool,
+    /// The directory the output is written to, if not `base_dir`; files code blocks refer to are
+    /// found from there
+    pub output_dir: Option<PathBuf>,
+}
+
+impl Options {
+    /// The style listings are written in unless they choose another
+    pub fn listing_style(&self) -> &str {
+        self.listing_style
+            .as_deref()
+            .unwrap_or(styles::DEFAULT_STYLE)
+    }
+}
 
-pub fn run(file_str: &str, path: &PathBuf) {
+/// Everything produced by a successful transpilation.
+#[derive(Debug, Clone)]
+pub struct Output {
+    pub tex: String,
+    /// Warnings found along the way
+    pub diagnostics: Vec<Diagnostic>,
+    pub source_map: SourceMap,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The source contains errors; holds every diagnostic found, warnings included
+    Diagnostics(Vec<Diagnostic>),
+    /// A [`Pass`] failed to transform the document
+    Pass(String),
+    /// A document in pandoc JSON could not be read
+    Pandoc(String),
+    /// A configuration file could not be read
+    Config(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "{}", error),
+            Error::Diagnostics(diagnostics) => {
+                let errors = diagnostics
+                    .iter()
+                    .filter(|diagnostic| diagnostic.severity == Severity::Error)
+                    .map(|diagnostic| diagnostic.to_string());
+                write!(f, "{}", errors.collect::<Vec<_>>().join("\n"))
+            }
+            Error::Pass(message) | Error::Pandoc(message) | Error::Config(message) => {
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            Error::Diagnostics(_) | Error::Pass(_) | Error::Pandoc(_) | Error::Config(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+/// Transpiles Markdown into a complete LaTeX document.
+///
+/// Warnings are discarded; use [`transpile_to`] to receive them.
+///
+/// ```
+/// let tex = saurus::transpile("# Heading", &saurus::Options::default()).unwrap();
+/// assert!(tex.contains(r"\section{Heading}"));
+/// assert!(tex.trim_end().ends_with(r"\end{document}"));
+/// ```
+pub fn transpile(input: &str, options: &Options) -> Result<String, Error> {
+    transpile_output(input, options).map(|output| output.tex)
+}
+
+/// Transpiles Markdown into a complete LaTeX document, writing it to `writer`.
+///
+/// Returns the warnings found along the way. If any diagnostic is an error,
+/// nothing is written and [`Error::Diagnostics`] is returned instead.
+pub fn transpile_to<W: Write>(
+    input: &str,
+    options: &Options,
+    mut writer: W,
+) -> Result<Vec<Diagnostic>, Error> {
+    let output = transpile_output(input, options)?;
+    writer.write_all(output.tex.as_bytes())?;
+    writer.flush()?;
+    Ok(output.diagnostics)
+}
+
+/// Transpiles Markdown into a complete LaTeX document along with its
+/// warnings and the map from TeX lines back to Markdown lines.
+///
+/// ```
+/// let output = saurus::transpile_output("Text\n\n# Heading", &saurus::Options::default()).unwrap();
+/// let line = output.tex.lines().position(|line| line == r"\section{Heading}").unwrap() + 1;
+/// assert_eq!(output.source_map.markdown_lines(line).unwrap().start, 3);
+/// ```
+pub fn transpile_output(input: &str, options: &Options) -> Result<Output, Error> {
+    transpile_with_passes(input, options, &mut [])
+}
+
+/// A transform run on the document tree between parsing and generation.
+///
+/// Closures taking a `&mut Document` are passes, as are any types
+/// implementing this trait, e.g. to wrap a [`visit::VisitorMut`].
+pub trait Pass {
+    fn run(&mut self, document: &mut Document) -> Result<(), Error>;
+
+    /// The warnings found by the last run, which the caller reports
+    fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        Vec::new()
+    }
+}
+
+impl<F> Pass for F
+where
+    F: FnMut(&mut Document) -> Result<(), Error>,
+{
+    fn run(&mut self, document: &mut Document) -> Result<(), Error> {
+        self(document)
+    }
+}
+
+/// Like [`transpile_output`], but runs `passes` in order on the parsed
+/// document before generating LaTeX from it.
+///
+/// ```
+/// use saurus::transpiler::ast::{self, Block, Document};
+/// use saurus::transpiler::{transpile_with_passes, Error, Options, Pass};
+///
+/// // Drops every section whose heading ends in "(draft)"
+/// let drop_drafts = |document: &mut Document| -> Result<(), Error> {
+///     let mut skip_below = None;
+///     document.blocks.retain(|block| {
+///         if let Block::Heading(heading) = block {
+///             if skip_below.map_or(true, |level| heading.level <= level) {
+///                 let draft = ast::plain_text(&heading.content).ends_with("(draft)");
+///                 skip_below = draft.then_some(heading.level);
+///             }
+///         }
+///         skip_below.is_none()
+///     });
+///     Ok(())
+/// };
+///
+/// let input = "# Done\n\nKept\n\n# Ideas (draft)\n\nDropped\n\n# Also done";
+/// let mut passes: Vec<Box<dyn Pass>> = vec![Box::new(drop_drafts)];
+/// let output = transpile_with_passes(input, &Options::default(), &mut passes).unwrap();
+/// assert!(output.tex.contains("Kept"));
+/// assert!(!output.tex.contains("Dropped"));
+/// assert!(output.tex.contains(r"\section{Also done}"));
+/// ```
+pub fn transpile_with_passes(
+    input: &str,
+    options: &Options,
+    passes: &mut [Box<dyn Pass>],
+) -> Result<Output, Error> {
+    let mut diagnostics = Vec::new();
+    let document = read_with_passes(input, options, passes, &mut diagnostics)?;
+    let (tex, source_map) = generate(&document, options, &mut diagnostics);
+    if options.strict {
+        diagnostics::deny_warnings(&mut diagnostics);
+    }
+
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+    if diagnostics::has_errors(&diagnostics) {
+        return Err(Error::Diagnostics(diagnostics));
+    }
+
+    Ok(Output {
+        tex,
+        diagnostics,
+        source_map,
+    })
+}
+
+/// Reads `input` into a document tree, with the files code blocks list
+/// resolved and `passes` run on it, which is everything [`transpile_with_passes`]
+/// does before generating. Warnings are pushed onto `diagnostics`.
+pub fn read_with_passes(
+    input: &str,
+    options: &Options,
+    passes: &mut [Box<dyn Pass>],
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Document, Error> {
+    let mut document = read(input, options, diagnostics)?;
+    include::resolve(&mut document, options, diagnostics);
+    if options.strict {
+        diagnostics::deny_warnings(diagnostics);
+    }
+    // Passes should only ever see documents that parsed cleanly
+    if diagnostics::has_errors(diagnostics) {
+        let mut diagnostics = mem::take(diagnostics);
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+        return Err(Error::Diagnostics(diagnostics));
+    }
+    for pass in passes.iter_mut() {
+        pass.run(&mut document)?;
+        diagnostics.append(&mut pass.take_diagnostics());
+    }
+    Ok(document)
+}
+
+/// Parses Markdown into a document tree, collecting any problems found into
+/// `diagnostics`.
+///
+/// ```
+/// use saurus::transpiler::{ast::Block, parse};
+///
+/// let document = parse("# Title\n\n- one\n- two", &mut Vec::new());
+/// assert!(matches!(document.blocks[0], Block::Heading(_)));
+/// match &document.blocks[1] {
+///     Block::List(list) => assert_eq!(list.items.len(), 2),
+///     block => panic!("expected a list, found {:?}", block),
+/// }
+///
+/// // A code fence right after a comment
+/// let mut diagnostics = Vec::new();
+/// let document = parse("<!-- note -->\n```python\nx = 1\n```\n\nafter", &mut diagnostics);
+/// match &document.blocks[0] {
+///     Block::CodeBlock(code_block) => {
+///         assert_eq!(code_block.language.as_deref(), Some("python"));
+///         assert_eq!(code_block.code, "x = 1");
+///     }
+///     block => panic!("expected a code block, found {:?}", block),
+/// }
+/// assert!(matches!(document.blocks[1], Block::Paragraph(_)));
+/// assert!(diagnostics.is_empty());
+/// ```
+pub fn parse(input: &str, diagnostics: &mut Vec<Diagnostic>) -> Document {
     let mut lex = lexer::Lexer::new();
-    lex.tokenize(&file_str);
+    lex.tokenize(input);
+    diagnostics.append(&mut lex.diagnostics);
 
     let mut parse = parser::Parser::new();
     parse.run(lex);
+    diagnostics.append(&mut parse.diagnostics);
+    parse.document()
+}
 
-    write(path, parse).unwrap_or_else(|error| {
-        println!("{}", error);
-    });
+/// Reads `input` in the format of `options.from` into a document tree.
+///
+/// ```
+/// use saurus::transpiler::{pandoc, parse, read, Options, SourceFormat};
+///
+/// let json = pandoc::to_json(&parse("- one\n- two", &mut Vec::new()));
+/// let options = Options { from: SourceFormat::PandocJson, ..Options::default() };
+/// let document = read(&json, &options, &mut Vec::new()).unwrap();
+/// let tex = saurus::transpiler::generate(&document, &options, &mut Vec::new()).0;
+/// assert!(tex.contains("\\item two"));
+/// ```
+pub fn read(
+    input: &str,
+    options: &Options,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Document, Error> {
+    match options.from {
+        SourceFormat::Markdown => Ok(parse(input, diagnostics)),
+        SourceFormat::PandocJson => pandoc::from_json(input, diagnostics),
+    }
+}
+
+/// Converts LaTeX in the subset saurus generates back into Markdown.
+///
+/// Transpiling the Markdown again gives back the same LaTeX body, so
+/// converting in both directions is stable:
+///
+/// ```
+/// use saurus::transpiler::{import, Options};
+///
+/// let tex = "\\section{Notes}\n\\begin{itemize}\n    \\item \\textbf{One} \\verb|1|\n\\end{itemize}";
+/// let markdown = import(tex, &mut Vec::new());
+/// assert_eq!(markdown, "# Notes\n\n- **One** `1`\n");
+///
+/// let again = saurus::transpile(&markdown, &Options::default()).unwrap();
+/// assert_eq!(import(&again, &mut Vec::new()), markdown);
+/// ```
+pub fn import(input: &str, diagnostics: &mut Vec<Diagnostic>) -> String {
+    let document = latex::parse(input, diagnostics);
+    markdown::render(&document, diagnostics)
+}
+
+/// Generates a complete document in the format of `options.target` from a
+/// document tree, along with the map from its lines back to the Markdown
+/// lines of the tree's spans.
+pub fn generate(
+    document: &Document,
+    options: &Options,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> (String, SourceMap) {
+    match options.target {
+        Target::Latex => generate_with(&mut Latex, document, options, diagnostics),
+        Target::Typst => generate_with(&mut Typst, document, options, diagnostics),
+        Target::Html => generate_with(&mut Html::new(), document, options, diagnostics),
+    }
 }
 
-fn write(path: &PathBuf, parser: parser::Parser) -> Result<(), Error> {
-    let mut file = fs::File::create(path)?;
-    writeln!(file, "{}", generator::documentclass())?;
-    writeln!(file, "{}", generator::packages(parser.contains_code_block))?;
-    for line in parser.results.into_iter() {
-        if let Some(line) = generator::generate_line(line) {
-            writeln!(file, "{}", line)?;
+/// Like [`generate`], but renders the document with `backend`.
+pub fn generate_with(
+    backend: &mut dyn Backend,
+    document: &Document,
+    options: &Options,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> (String, SourceMap) {
+    let mut cx = Context::new(options, diagnostics);
+    let fragments: Vec<Vec<Fragment>> = document
+        .blocks
+        .iter()
+        .map(|block| backend.block(block, &mut cx))
+        .collect();
+
+    let mut text = backend.prologue(&mut cx);
+    let mut source_map = SourceMap::default();
+    let mut lines = text.lines().count();
+    for block in fragments {
+        // Blocks the format has no output for
+        if block.is_empty() {
+            continue;
+        }
+        for fragment in block {
+            let mapped = fragment.span.start > 0;
+            if options.line_comments && mapped {
+                let comment = backend.comment(&format!("md:{}", fragment.span));
+                text.push_str(&format!("{}\n", comment));
+                lines += 1;
+            }
+            let length = fragment.text.lines().count();
+            if mapped && length > 0 {
+                source_map.push(Span::new(lines + 1, lines + length), fragment.span);
+            }
+            text.push_str(&format!("{}\n", fragment.text));
+            lines += length;
         }
+        text.push('\n');
+        lines += 1;
     }
-    Ok(())
+    text.push_str(&backend.epilogue(&mut cx));
+    (text, source_map)
 }
@@ -0,0 +1,207 @@
+use serde::Serialize;
+
+use crate::transpiler::lexer::Span;
+
+/// A parsed Markdown document.
+///
+/// Documents can also be built by hand and rendered with
+/// [`generate`](crate::transpiler::generate):
+///
+/// ```
+/// use saurus::transpiler::ast::{Block, Document, Heading, Inline};
+/// use saurus::transpiler::{generate, lexer::Span, Options};
+///
+/// let document = Document {
+///     blocks: vec![Block::Heading(Heading {
+///         level: 1,
+///         content: vec![Inline::Text("Built by hand".to_string())],
+///         span: Span::default(),
+///     })],
+/// };
+/// let (tex, _) = generate(&document, &Options::default(), &mut Vec::new());
+/// assert!(tex.contains(r"\section{Built by hand}"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Document {
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Block {
+    Heading(Heading),
+    Paragraph(Paragraph),
+    List(List),
+    Quote(Quote),
+    CodeBlock(CodeBlock),
+    RawLatex(RawLatex),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Heading {
+    pub level: usize,
+    pub content: Vec<Inline>,
+    pub span: Span,
+}
+
+/// Consecutive lines of text, separated by [`Inline::SoftBreak`]s.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Paragraph {
+    pub content: Vec<Inline>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ListKind {
+    Unordered,
+    /// Numbered list counting up from `start`
+    Ordered {
+        start: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct List {
+    pub kind: ListKind,
+    pub items: Vec<Item>,
+    pub span: Span,
+}
+
+/// A list item along with any blocks nested beneath it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Item {
+    pub content: Vec<Inline>,
+    pub children: Vec<Block>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Quote {
+    pub blocks: Vec<Block>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CodeBlock {
+    pub language: Option<String>,
+    pub attributes: CodeAttributes,
+    /// The verbatim lines of code, joined by `\n`
+    pub code: String,
+    pub span: Span,
+}
+
+/// LaTeX written into LaTeX output as is, such as a pandoc `RawBlock`.
+/// Other formats leave it out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RawLatex {
+    pub latex: String,
+    pub span: Span,
+}
+
+/// Options given in a code fence's info string, such as
+/// `{.python #lst:solver caption="Solver" linenos startFrom=10 hl=3-5}`
+/// or `rust file=src/main.rs lines=10-40 style=dark`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CodeAttributes {
+    /// What text can reference the listing by
+    pub label: Option<String>,
+    pub caption: Option<String>,
+    pub line_numbers: bool,
+    /// The number of the first line, if not 1
+    pub first_line: Option<usize>,
+    /// Inclusive ranges of lines to highlight, numbered like the lines are
+    pub highlight: Vec<(usize, usize)>,
+    /// A file to list in place of the code, relative to the Markdown file
+    pub file: Option<String>,
+    /// The inclusive range of the file's lines to list
+    pub lines: Option<(usize, usize)>,
+    /// The listing style, if not the document's
+    pub style: Option<String>,
+}
+
+impl CodeAttributes {
+    pub fn is_empty(&self) -> bool {
+        *self == CodeAttributes::default()
+    }
+
+    /// The number of the first line
+    pub fn first_line(&self) -> usize {
+        self.first_line.unwrap_or(1)
+    }
+
+    pub fn is_highlighted(&self, line: usize) -> bool {
+        self.highlight
+            .iter()
+            .any(|(start, end)| *start <= line && line <= *end)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Inline {
+    Text(String),
+    Bold(Vec<Inline>),
+    Italic(Vec<Inline>),
+    Strike(Vec<Inline>),
+    Code(String),
+    Link {
+        content: Vec<Inline>,
+        url: String,
+    },
+    Math(String),
+    /// LaTeX written into LaTeX output as is, like [`RawLatex`]
+    RawLatex(String),
+    /// A line break in the source that does not force one in the output
+    SoftBreak,
+    LineBreak,
+}
+
+impl Block {
+    pub fn span(&self) -> Span {
+        match self {
+            Block::Heading(heading) => heading.span,
+            Block::Paragraph(paragraph) => paragraph.span,
+            Block::List(list) => list.span,
+            Block::Quote(quote) => quote.span,
+            Block::CodeBlock(code_block) => code_block.span,
+            Block::RawLatex(raw) => raw.span,
+        }
+    }
+}
+
+impl Document {
+    pub fn contains_code_block(&self) -> bool {
+        fn any_code(blocks: &[Block]) -> bool {
+            blocks.iter().any(|block| match block {
+                Block::CodeBlock(_) => true,
+                Block::List(list) => list.items.iter().any(|item| any_code(&item.children)),
+                Block::Quote(quote) => any_code(&quote.blocks),
+                _ => false,
+            })
+        }
+        any_code(&self.blocks)
+    }
+}
+
+/// Concatenates the text of `inlines`, dropping all formatting.
+///
+/// ```
+/// use saurus::transpiler::{ast, inline};
+///
+/// assert_eq!(ast::plain_text(&inline::parse("**Bold** and `code`")), "Bold and code");
+/// ```
+pub fn plain_text(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(contents) | Inline::Code(contents) | Inline::Math(contents) => {
+                text.push_str(contents)
+            }
+            Inline::Bold(content)
+            | Inline::Italic(content)
+            | Inline::Strike(content)
+            | Inline::Link { content, .. } => text.push_str(&plain_text(content)),
+            Inline::SoftBreak | Inline::LineBreak => text.push(' '),
+            Inline::RawLatex(_) => {}
+        }
+    }
+    text
+}
@@ -0,0 +1,278 @@
+//! Rendering of the document tree into an output format.
+//!
+//! Every method of [`Backend`] defaults to the LaTeX output of
+//! [`generator`], so [`Latex`] is simply the trait with nothing overridden.
+//! Other formats override every method, while a custom LaTeX flavour can
+//! override just the nodes it renders differently:
+//!
+//! ```
+//! use saurus::transpiler::ast::Inline;
+//! use saurus::transpiler::backend::{Backend, Context};
+//! use saurus::transpiler::{generate_with, parse, Options};
+//!
+//! struct SmallCaps;
+//!
+//! impl Backend for SmallCaps {
+//!     fn bold(&mut self, content: &[Inline], cx: &mut Context) -> String {
+//!         format!("\\textsc{{{}}}", self.inlines(content, cx))
+//!     }
+//! }
+//!
+//! let document = parse("**Note:** text", &mut Vec::new());
+//! let (tex, _) = generate_with(&mut SmallCaps, &document, &Options::default(), &mut Vec::new());
+//! assert!(tex.contains(r"\textsc{Note:} text"));
+//! ```
+
+use std::collections::BTreeSet;
+
+use crate::transpiler::ast::{Block, CodeBlock, Heading, Inline, List, Paragraph, Quote, RawLatex};
+use crate::transpiler::code_blocks::CodeBackend;
+use crate::transpiler::diagnostics::Diagnostic;
+use crate::transpiler::generator;
+use crate::transpiler::lexer::Span;
+use crate::transpiler::Options;
+
+/// A piece of generated output and the Markdown lines it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fragment {
+    pub text: String,
+    pub span: Span,
+}
+
+impl Fragment {
+    pub fn new(text: String, span: Span) -> Self {
+        Fragment { text, span }
+    }
+}
+
+/// Something the rendered blocks need from the document's prologue.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Resource {
+    CodeBlocks,
+    /// A listings language code is written in
+    Language(String),
+    /// Code blocks with highlighted lines
+    HighlightedLines,
+    /// A listing style, by name
+    ListingStyle(String),
+    Links,
+    StrikeOut,
+    Math,
+    /// Anything a custom backend wants to keep track of
+    Custom(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Resources {
+    pub required: BTreeSet<Resource>,
+}
+
+impl Resources {
+    pub fn require(&mut self, resource: Resource) {
+        self.required.insert(resource);
+    }
+
+    pub fn contains(&self, resource: &Resource) -> bool {
+        self.required.contains(resource)
+    }
+
+    /// The listing styles of all code blocks rendered
+    pub fn listing_styles(&self) -> impl Iterator<Item = &str> {
+        self.required.iter().filter_map(|resource| match resource {
+            Resource::ListingStyle(style) => Some(style.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The listings languages of all code blocks rendered
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.required.iter().filter_map(|resource| match resource {
+            Resource::Language(language) => Some(language.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// State shared by every render call for a single document.
+pub struct Context<'a> {
+    pub options: &'a Options,
+    pub diagnostics: &'a mut Vec<Diagnostic>,
+    pub resources: Resources,
+    /// How many lists deep the block being rendered is
+    pub depth: usize,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(options: &'a Options, diagnostics: &'a mut Vec<Diagnostic>) -> Self {
+        Context {
+            options,
+            diagnostics,
+            resources: Resources::default(),
+            depth: 0,
+        }
+    }
+}
+
+pub trait Backend {
+    /// Everything before the first block, written once the blocks have been
+    /// rendered so that only the resources they required are set up.
+    fn prologue(&mut self, cx: &mut Context) -> String {
+        generator::prologue(&cx.resources, cx.options)
+    }
+
+    fn epilogue(&mut self, _cx: &mut Context) -> String {
+        generator::epilogue()
+    }
+
+    /// A single line comment holding `text`
+    fn comment(&mut self, text: &str) -> String {
+        format!("% {}", text)
+    }
+
+    fn block(&mut self, block: &Block, cx: &mut Context) -> Vec<Fragment> {
+        match block {
+            Block::Heading(heading) => self.heading(heading, cx),
+            Block::Paragraph(paragraph) => self.paragraph(paragraph, cx),
+            Block::List(list) => self.list(list, cx),
+            Block::Quote(quote) => self.quote(quote, cx),
+            Block::CodeBlock(code_block) => {
+                cx.resources.require(Resource::CodeBlocks);
+                if !code_block.attributes.highlight.is_empty() {
+                    cx.resources.require(Resource::HighlightedLines);
+                }
+                self.code_block(code_block, cx)
+            }
+            Block::RawLatex(raw) => self.raw_block(raw, cx),
+        }
+    }
+
+    fn heading(&mut self, heading: &Heading, cx: &mut Context) -> Vec<Fragment> {
+        generator::heading(self, heading, cx)
+    }
+
+    fn paragraph(&mut self, paragraph: &Paragraph, cx: &mut Context) -> Vec<Fragment> {
+        let text = self.inlines(&paragraph.content, cx);
+        vec![Fragment::new(text, paragraph.span)]
+    }
+
+    fn list(&mut self, list: &List, cx: &mut Context) -> Vec<Fragment> {
+        generator::listify(self, list, cx)
+    }
+
+    fn quote(&mut self, quote: &Quote, cx: &mut Context) -> Vec<Fragment> {
+        generator::block_quote(self, quote, cx)
+    }
+
+    fn code_block(&mut self, code_block: &CodeBlock, cx: &mut Context) -> Vec<Fragment> {
+        match cx.options.code_backend {
+            CodeBackend::Listings => generator::code_block(code_block, cx),
+            CodeBackend::Minted => generator::minted(code_block, cx),
+            CodeBackend::Verbatim => generator::verbatim(code_block, cx),
+            CodeBackend::Fancyvrb => generator::fancyvrb(code_block, cx),
+            CodeBackend::Builtin => generator::builtin(code_block, cx),
+        }
+    }
+
+    fn raw_block(&mut self, raw: &RawLatex, _cx: &mut Context) -> Vec<Fragment> {
+        vec![Fragment::new(raw.latex.clone(), raw.span)]
+    }
+
+    fn inlines(&mut self, inlines: &[Inline], cx: &mut Context) -> String {
+        let mut text = String::new();
+        for inline in inlines {
+            text.push_str(&self.inline(inline, cx));
+        }
+        text
+    }
+
+    fn inline(&mut self, inline: &Inline, cx: &mut Context) -> String {
+        match inline {
+            Inline::Text(text) => self.text(text, cx),
+            Inline::Bold(content) => self.bold(content, cx),
+            Inline::Italic(content) => self.italic(content, cx),
+            Inline::Strike(content) => {
+                cx.resources.require(Resource::StrikeOut);
+                self.strike(content, cx)
+            }
+            Inline::Code(code) => self.code(code, cx),
+            Inline::Link { content, url } => {
+                cx.resources.require(Resource::Links);
+                self.link(content, url, cx)
+            }
+            Inline::Math(math) => {
+                cx.resources.require(Resource::Math);
+                self.math(math, cx)
+            }
+            Inline::RawLatex(latex) => self.raw_inline(latex, cx),
+            Inline::SoftBreak => self.soft_break(cx),
+            Inline::LineBreak => self.line_break(cx),
+        }
+    }
+
+    fn text(&mut self, text: &str, _cx: &mut Context) -> String {
+        generator::escape(text)
+    }
+
+    fn bold(&mut self, content: &[Inline], cx: &mut Context) -> String {
+        format!("\\textbf{{{}}}", self.inlines(content, cx))
+    }
+
+    fn italic(&mut self, content: &[Inline], cx: &mut Context) -> String {
+        format!("\\textit{{{}}}", self.inlines(content, cx))
+    }
+
+    fn strike(&mut self, content: &[Inline], cx: &mut Context) -> String {
+        format!("\\sout{{{}}}", self.inlines(content, cx))
+    }
+
+    fn code(&mut self, code: &str, _cx: &mut Context) -> String {
+        generator::verb(code)
+    }
+
+    fn link(&mut self, content: &[Inline], url: &str, cx: &mut Context) -> String {
+        format!(
+            "\\href{{{}}}{{{}}}",
+            url.replace('%', "\\%").replace('#', "\\#"),
+            self.inlines(content, cx)
+        )
+    }
+
+    fn math(&mut self, math: &str, _cx: &mut Context) -> String {
+        format!("${}$", math)
+    }
+
+    fn raw_inline(&mut self, latex: &str, _cx: &mut Context) -> String {
+        latex.to_string()
+    }
+
+    fn soft_break(&mut self, _cx: &mut Context) -> String {
+        "\n".to_string()
+    }
+
+    fn line_break(&mut self, _cx: &mut Context) -> String {
+        "\\\\\n".to_string()
+    }
+}
+
+/// Warns that raw LaTeX from `line`, 0 if unknown, is left out of output
+/// that is not LaTeX.
+pub fn drop_raw_latex(line: usize, cx: &mut Context) {
+    let diagnostic = Diagnostic::warning(
+        line,
+        1,
+        format!(
+            "raw LaTeX cannot be written as {}",
+            cx.options.target.name()
+        ),
+    )
+    .with_hint("it is left out");
+    if !cx.diagnostics.contains(&diagnostic) {
+        cx.diagnostics.push(diagnostic);
+    }
+}
+
+/// The default LaTeX output.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Latex;
+
+impl Backend for Latex {}
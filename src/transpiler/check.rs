@@ -0,0 +1,222 @@
+//! Lints for Markdown that saurus renders badly, or not at all.
+//!
+//! ```
+//! use saurus::transpiler::check;
+//!
+//! let source = "# Title\n\n### Skipped\n\nSome **bold text\n";
+//! let diagnostics = check::check(source, &saurus::Options::default());
+//! let messages: Vec<_> = diagnostics.iter().map(|d| (d.line, d.message.as_str())).collect();
+//! assert_eq!(messages, vec![(3, "heading level skipped"), (5, "unbalanced `**`")]);
+//! ```
+
+use std::collections::HashSet;
+
+use regex::Regex;
+
+use crate::transpiler::ast::Block;
+use crate::transpiler::diagnostics::Diagnostic;
+use crate::transpiler::{generate, include, parse, Options};
+
+/// Markdown saurus has no support for yet, and what to call it
+const UNSUPPORTED: [(&str, &str); 8] = [
+    (r"^\s*\|.*\|\s*$", "tables are not supported"),
+    (r"!\[[^\]]*\]\(", "images are not supported"),
+    (r"<[A-Za-z/][^>]*>", "HTML is not supported"),
+    (
+        r"^\s*(-\s*){3,}$|^\s*(\*\s*){3,}$|^\s*(_\s*){3,}$",
+        "horizontal rules are not supported",
+    ),
+    (r"^\s*=+\s*$", "setext headings are not supported"),
+    (r"\[\^[^\]]+\]", "footnotes are not supported"),
+    (r"^\s*\[[^\]]+\]:\s*\S", "reference links are not supported"),
+    (r"^\s*[-+*]\s+\[[ xX]\]", "task lists are not supported"),
+];
+
+/// Checks `source` for everything saurus would warn about or reject, along
+/// with Markdown it does not support and constructs likely to upset LaTeX.
+pub fn check(source: &str, options: &Options) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut document = parse(source, &mut diagnostics);
+    include::resolve(&mut document, options, &mut diagnostics);
+    // Generating reports what the generator itself would warn about
+    generate(&document, options, &mut diagnostics);
+
+    let mut previous_level = None;
+    for block in document.blocks.iter() {
+        if let Block::Heading(heading) = block {
+            if previous_level.is_some_and(|previous| heading.level > previous + 1) {
+                diagnostics.push(
+                    Diagnostic::warning(heading.span.start, 1, "heading level skipped").with_hint(
+                        format!(
+                            "the previous heading has level {}",
+                            previous_level.unwrap_or_default()
+                        ),
+                    ),
+                );
+            }
+            previous_level = Some(heading.level);
+        }
+    }
+
+    let code: HashSet<usize> = document
+        .blocks
+        .iter()
+        .filter(|block| matches!(block, Block::CodeBlock(_)))
+        .flat_map(|block| block.span().start..=block.span().end)
+        .collect();
+    let unsupported: Vec<(Regex, &str)> = UNSUPPORTED
+        .iter()
+        .map(|(pattern, message)| (Regex::new(pattern).unwrap(), *message))
+        .collect();
+    let list_item = Regex::new(r"^\s*([-+*]|\w+[\.\)])\s").unwrap();
+    let mut explained = HashSet::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let number = index + 1;
+        if code.contains(&number) || line.trim().is_empty() || line.trim_start().starts_with("<!--")
+        {
+            continue;
+        }
+
+        for (pattern, message) in unsupported.iter() {
+            if let Some(found) = pattern.find(line) {
+                diagnostics.push(Diagnostic::warning(number, found.start() + 1, *message));
+                explained.insert(number);
+            }
+        }
+
+        if list_item.is_match(line) {
+            let indent = line.len() - line.trim_start().len();
+            if line[..indent].contains('\t') {
+                diagnostics.push(
+                    Diagnostic::warning(number, 1, "list indented with tabs")
+                        .with_hint("indent nested lists by four spaces"),
+                );
+            } else if indent % 4 != 0 {
+                diagnostics.push(
+                    Diagnostic::warning(number, 1, format!("list indented by {} spaces", indent))
+                        .with_hint(format!(
+                            "indentation is counted in fours, so this is read as {} spaces",
+                            indent / 4 * 4
+                        )),
+                );
+            }
+        }
+
+        check_inline(line, number, &mut diagnostics);
+    }
+
+    // Saying why a line is ignored is more useful than saying that it is
+    diagnostics.retain(|diagnostic| {
+        diagnostic.message != "line is ignored" || !explained.contains(&diagnostic.line)
+    });
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+    diagnostics
+}
+
+/// Reports unbalanced markers and LaTeX special characters in a line.
+fn check_inline(line: &str, number: usize, diagnostics: &mut Vec<Diagnostic>) {
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let mut backticks = Vec::new();
+    let mut strikes = Vec::new();
+    let mut bolds = Vec::new();
+    let mut specials = Vec::new();
+    let mut raw_latex = false;
+    let mut in_code = false;
+    let mut in_math = false;
+
+    let mut index = 0;
+    while index < chars.len() {
+        let (byte, c) = chars[index];
+        let next = chars.get(index + 1).map(|(_, c)| *c);
+        if in_code {
+            if c == '`' {
+                in_code = false;
+                backticks.push(byte);
+            }
+            index += 1;
+            continue;
+        }
+        if in_math {
+            in_math = c != '$';
+            index += 1;
+            continue;
+        }
+        match c {
+            '\\' => {
+                let name: String = chars[index + 1..]
+                    .iter()
+                    .map(|(_, c)| *c)
+                    .take_while(char::is_ascii_alphabetic)
+                    .collect();
+                if !name.is_empty() {
+                    raw_latex = true;
+                    specials.push((
+                        byte,
+                        format!("raw LaTeX command `\\{}`", name),
+                        "it is passed to LaTeX unchanged; write `\\textbackslash{}` for a literal backslash",
+                    ));
+                }
+                index += 1 + name.chars().count().max(1);
+                continue;
+            }
+            '`' => {
+                in_code = true;
+                backticks.push(byte);
+            }
+            '$' if !next.is_some_and(|next| next.is_ascii_digit()) => in_math = true,
+            '~' if next == Some('~') => {
+                strikes.push(byte);
+                index += 2;
+                continue;
+            }
+            '~' => specials.push((
+                byte,
+                "`~` is a non-breaking space in LaTeX".to_string(),
+                "write `\\textasciitilde{}` for a literal tilde",
+            )),
+            '^' => specials.push((
+                byte,
+                "`^` is only allowed in math in LaTeX".to_string(),
+                "write `\\textasciicircum{}` for a literal caret",
+            )),
+            '{' | '}' => specials.push((
+                byte,
+                format!("`{}` groups text in LaTeX", c),
+                "write `\\{` or `\\}` for a literal brace",
+            )),
+            '*' => {
+                let run = chars[index..].iter().take_while(|(_, c)| *c == '*').count();
+                if run >= 2 {
+                    bolds.push(byte);
+                }
+                index += run;
+                continue;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+
+    for (markers, name) in [
+        (&backticks, "backtick"),
+        (&strikes, "`~~`"),
+        (&bolds, "`**`"),
+    ] {
+        if markers.len() % 2 == 1 {
+            let column = markers[markers.len() - 1] + 1;
+            diagnostics.push(
+                Diagnostic::warning(number, column, format!("unbalanced {}", name)).with_hint(
+                    "it is written out literally; escape it with `\\` if that is intended",
+                ),
+            );
+        }
+    }
+    for (byte, message, hint) in specials {
+        // Braces after a raw command are most likely its arguments
+        if raw_latex && message.contains("groups text") {
+            continue;
+        }
+        diagnostics.push(Diagnostic::warning(number, byte + 1, message).with_hint(hint));
+    }
+}
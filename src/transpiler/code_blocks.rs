@@ -1,13 +1,502 @@
+use std::collections::HashMap;
+use std::mem;
+
+use serde::Deserialize;
+
+use crate::transpiler::ast::CodeAttributes;
+use crate::transpiler::diagnostics::Diagnostic;
+
+/// What a code block gets when listings does not know its language.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub enum Fallback {
+    /// A plain listing, without highlighting
+    None,
+    /// The given listings language
+    Language(String),
+    /// Reject the document
+    Error,
+}
+
+impl Default for Fallback {
+    fn default() -> Self {
+        Fallback::Language("python".to_string())
+    }
+}
+
+/// Reads `"none"` and `"error"` as themselves, anything else as a language.
+impl From<String> for Fallback {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "none" => Fallback::None,
+            "error" => Fallback::Error,
+            _ => Fallback::Language(name),
+        }
+    }
+}
+
+/// The LaTeX package code blocks are written for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CodeBackend {
+    /// `lstlisting`, highlighted by listings itself
+    #[default]
+    Listings,
+    /// `minted`, highlighted by Pygments, which needs `-shell-escape`
+    Minted,
+    /// Plain `verbatim`, without highlighting or line numbers
+    Verbatim,
+    /// fancyvrb's `Verbatim`, with line numbers but no highlighting
+    Fancyvrb,
+    /// fancyvrb's `Verbatim`, colored by saurus itself with bundled grammars
+    Builtin,
+}
+
+impl CodeBackend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CodeBackend::Listings => "listings",
+            CodeBackend::Minted => "minted",
+            CodeBackend::Verbatim => "verbatim",
+            CodeBackend::Fancyvrb => "fancyvrb",
+            CodeBackend::Builtin => "builtin",
+        }
+    }
+}
+
+/// How code block languages are turned into listings languages.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Languages {
+    pub fallback: Fallback,
+    /// Languages to write as another, e.g. `"rust"` as `"c"`
+    pub map: HashMap<String, String>,
+}
+
+impl Languages {
+    /// The listings language for `language`, or `Err` with the language that
+    /// listings does not know. Mapped languages may be aliases too.
+    ///
+    /// ```
+    /// use saurus::transpiler::code_blocks::Languages;
+    ///
+    /// let mut languages = Languages::default();
+    /// languages.map.insert("rust".to_string(), "c".to_string());
+    /// assert_eq!(languages.resolve("rust"), Ok("c".to_string()));
+    /// assert_eq!(languages.resolve("rs"), Ok("rust".to_string()));
+    /// assert_eq!(languages.resolve("bash"), Ok("bash".to_string()));
+    /// assert_eq!(languages.resolve("zig"), Err("zig".to_string()));
+    /// ```
+    pub fn resolve(&self, language: &str) -> Result<String, String> {
+        let language = alias(self.map.get(language).map_or(language, String::as_str));
+        if is_invalid_language(language) {
+            Err(language.to_string())
+        } else {
+            Ok(language.to_string())
+        }
+    }
+}
+
+/// Splits a code fence's info string into its language and attributes.
+///
+/// Both `python linenos` and pandoc's `{.python #lst:solver startFrom=10}`
+/// are understood. Attributes saurus does not know are reported and ignored;
+/// `line` and `column` locate the info string in the source.
+///
+/// ```
+/// use saurus::transpiler::code_blocks;
+///
+/// let info = r#"{.python #lst:solver caption="The solver" linenos startFrom=10 hl=3-5}"#;
+/// let (language, attributes) = code_blocks::parse_info(info, 1, 4, &mut Vec::new());
+/// assert_eq!(language.as_deref(), Some("python"));
+/// assert_eq!(attributes.label.as_deref(), Some("lst:solver"));
+/// assert_eq!(attributes.caption.as_deref(), Some("The solver"));
+/// assert!(attributes.line_numbers);
+/// assert_eq!(attributes.first_line, Some(10));
+/// assert_eq!(attributes.highlight, vec![(3, 5)]);
+/// assert_eq!(code_blocks::info_string(language.as_deref(), &attributes), info);
+///
+/// let (_, attributes) = code_blocks::parse_info("rust file=src/main.rs lines=10-40", 1, 4, &mut Vec::new());
+/// assert_eq!(attributes.file.as_deref(), Some("src/main.rs"));
+/// assert_eq!(attributes.lines, Some((10, 40)));
+/// ```
+pub fn parse_info(
+    info: &str,
+    line: usize,
+    column: usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> (Option<String>, CodeAttributes) {
+    let mut language = None;
+    let mut attributes = CodeAttributes::default();
+    let (words, closed) = words(info);
+    if !closed {
+        diagnostics.push(
+            Diagnostic::warning(line, column, "unterminated quote in code block attributes")
+                .with_hint("the rest of the line is read as one value"),
+        );
+    }
+    let mut warn = |message: String| {
+        diagnostics.push(Diagnostic::warning(line, column, message).with_hint("it is ignored"));
+    };
+
+    for (index, word) in words.iter().enumerate() {
+        if let Some(class) = word.strip_prefix('.') {
+            match class {
+                "numberLines" | "number-lines" | "linenos" => attributes.line_numbers = true,
+                _ if language.is_none() => language = Some(class.to_string()),
+                _ => warn(format!("code block has a second language `{}`", class)),
+            }
+        } else if let Some(label) = word.strip_prefix('#') {
+            attributes.label = Some(label.to_string());
+        } else if let Some((key, value)) = word.split_once('=') {
+            match key {
+                "caption" | "title" => attributes.caption = Some(value.to_string()),
+                "label" | "id" => attributes.label = Some(value.to_string()),
+                "startFrom" | "start-from" | "firstnumber" => match value.parse() {
+                    Ok(first_line) => attributes.first_line = Some(first_line),
+                    Err(_) => warn(format!("`{}` is not a line number", value)),
+                },
+                "hl" | "hl_lines" | "highlight" => match ranges(value) {
+                    Some(ranges) => attributes.highlight.extend(ranges),
+                    None => warn(format!("`{}` is not a list of lines, e.g. `3-5,8`", value)),
+                },
+                "file" | "include" => attributes.file = Some(value.to_string()),
+                "style" => attributes.style = Some(value.to_string()),
+                "lines" => match ranges(value).as_deref() {
+                    Some([lines]) => attributes.lines = Some(*lines),
+                    _ => warn(format!("`{}` is not a range of lines, e.g. `10-40`", value)),
+                },
+                _ => warn(format!("unknown code block attribute `{}`", key)),
+            }
+        } else {
+            match word.as_str() {
+                "linenos" | "numberLines" | "number-lines" => attributes.line_numbers = true,
+                _ if index == 0 => language = Some(word.to_string()),
+                _ => warn(format!("unknown code block attribute `{}`", word)),
+            }
+        }
+    }
+    (language, attributes)
+}
+
+/// Writes a language and attributes back as an info string.
+pub fn info_string(language: Option<&str>, attributes: &CodeAttributes) -> String {
+    if attributes.is_empty() {
+        return language.unwrap_or_default().to_string();
+    }
+    let mut words = Vec::new();
+    if let Some(language) = language {
+        words.push(format!(".{}", language));
+    }
+    if let Some(label) = &attributes.label {
+        words.push(format!("#{}", label));
+    }
+    if let Some(caption) = &attributes.caption {
+        words.push(format!("caption=\"{}\"", caption));
+    }
+    if attributes.line_numbers {
+        words.push("linenos".to_string());
+    }
+    if let Some(first_line) = attributes.first_line {
+        words.push(format!("startFrom={}", first_line));
+    }
+    if !attributes.highlight.is_empty() {
+        words.push(format!("hl={}", ranges_string(&attributes.highlight)));
+    }
+    if let Some(file) = &attributes.file {
+        words.push(format!("file=\"{}\"", file));
+    }
+    if let Some(lines) = attributes.lines {
+        words.push(format!("lines={}", ranges_string(&[lines])));
+    }
+    if let Some(style) = &attributes.style {
+        words.push(format!("style={}", style));
+    }
+    format!("{{{}}}", words.join(" "))
+}
+
+/// Writes inclusive ranges of lines the way `hl=` reads them, e.g. `3-5,8`.
+pub fn ranges_string(ranges: &[(usize, usize)]) -> String {
+    ranges
+        .iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The whitespace separated words of an info string, without quotes or
+/// the braces around attributes, and whether every quote was closed.
+fn words(info: &str) -> (Vec<String>, bool) {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in info.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            _ if quoted => word.push(c),
+            '{' | '}' => {}
+            _ if c.is_whitespace() => {
+                if !word.is_empty() {
+                    words.push(mem::take(&mut word));
+                }
+            }
+            _ => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    (words, !quoted)
+}
+
+/// Reads lines such as `3-5,8` as inclusive ranges.
+fn ranges(value: &str) -> Option<Vec<(usize, usize)>> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|range| !range.is_empty())
+        .map(|range| {
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+            (0 < start && start <= end).then_some((start, end))
+        })
+        .collect()
+}
+
 // List of all languages can also be found at [TeXDoc](https://texdoc.org/serve/listings.pdf/0) on page 13
 pub fn is_invalid_language(language: &str) -> bool {
     LISTINGS_LANGUAGES
         .iter()
+        .chain(DEFINITIONS.iter().map(|(name, _)| name))
         .filter(|listings_languages| language == **listings_languages)
         .count()
         == 0
 }
 
-pub const LISTINGS_LANGUAGES: [&'static str; 94] = [
+/// Common fence tags and the language they stand for
+pub const ALIASES: [(&str, &str); 9] = [
+    ("py", "python"),
+    ("js", "javascript"),
+    ("ts", "typescript"),
+    ("rs", "rust"),
+    ("cpp", "c++"),
+    ("shell", "bash"),
+    ("yml", "yaml"),
+    ("kt", "kotlin"),
+    ("docker", "dockerfile"),
+];
+
+/// The language `language` is short for, or `language` itself.
+///
+/// ```
+/// use saurus::transpiler::code_blocks;
+/// assert_eq!(code_blocks::alias("py"), "python");
+/// assert_eq!(code_blocks::alias("python"), "python");
+/// ```
+pub fn alias(language: &str) -> &str {
+    ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == language)
+        .map_or(language, |(_, name)| name)
+}
+
+/// The `\lstdefinelanguage` for a language listings does not know, if
+/// saurus bundles one.
+pub fn definition(language: &str) -> Option<&'static str> {
+    DEFINITIONS
+        .iter()
+        .find(|(name, _)| *name == language)
+        .map(|(_, definition)| *definition)
+}
+
+/// The Pygments lexer minted highlights a code block language with, given
+/// as written in the source. Pygments knows most languages and their short
+/// names itself, including many listings lacks; only names that are
+/// listings' own are translated.
+///
+/// ```
+/// use saurus::transpiler::code_blocks;
+/// assert_eq!(code_blocks::pygments_lexer("c++"), "cpp");
+/// assert_eq!(code_blocks::pygments_lexer("rust"), "rust");
+/// assert_eq!(code_blocks::pygments_lexer("py"), "py");
+/// assert_eq!(code_blocks::pygments_lexer("zig"), "zig");
+/// assert_eq!(code_blocks::pygments_lexer("elixir"), "elixir");
+/// assert_eq!(code_blocks::pygments_lexer("mizar"), "text");
+/// ```
+pub fn pygments_lexer(language: &str) -> &str {
+    if NO_PYGMENTS_LEXER.contains(&language) {
+        return "text";
+    }
+    PYGMENTS_LEXERS
+        .iter()
+        .find(|(name, _)| *name == language)
+        .map_or(language, |(_, lexer)| lexer)
+}
+
+/// Listings languages whose Pygments lexer has another name
+const PYGMENTS_LEXERS: [(&str, &str); 13] = [
+    ("c++", "cpp"),
+    ("assembler", "nasm"),
+    ("command.com", "batch"),
+    ("elisp", "emacs-lisp"),
+    ("lisp", "common-lisp"),
+    ("ml", "sml"),
+    ("modula-2", "modula2"),
+    ("pascal", "delphi"),
+    ("s", "splus"),
+    ("sh", "bash"),
+    ("ant", "xml"),
+    ("basic", "qbasic"),
+    ("dockerfile", "docker"),
+];
+
+/// Listings languages Pygments has no lexer for
+const NO_PYGMENTS_LEXER: [&str; 26] = [
+    "acm",
+    "acmscript",
+    "acsl",
+    "algol",
+    "cil",
+    "clean",
+    "comal 80",
+    "comsol",
+    "elan",
+    "euphoria",
+    "gcl",
+    "hansl",
+    "jvmis",
+    "lingo",
+    "metapost",
+    "miranda",
+    "mizar",
+    "mupad",
+    "nastran",
+    "ocl",
+    "pl/i",
+    "plasm",
+    "pstricks",
+    "shelxl",
+    "simula",
+    "vrml",
+];
+
+/// Languages saurus defines for listings, written into the preamble of
+/// documents that use them
+pub const DEFINITIONS: [(&str, &str); 8] = [
+    (
+        "rust",
+        r#"
+    \lstdefinelanguage{rust}{
+        sensitive=true,
+        morekeywords={as, async, await, break, const, continue, crate, dyn, else, enum, extern,
+            false, fn, for, if, impl, in, let, loop, match, mod, move, mut, pub, ref, return,
+            self, Self, static, struct, super, trait, true, type, unsafe, use, where, while},
+        morekeywords=[2]{bool, char, f32, f64, i8, i16, i32, i64, i128, isize, str, u8, u16,
+            u32, u64, u128, usize, String, Vec, Option, Result, Box, Some, None, Ok, Err},
+        morecomment=[l]{//},
+        morecomment=[s]{/*}{*/},
+        morestring=[b]",
+    }"#,
+    ),
+    (
+        "javascript",
+        r#"
+    \lstdefinelanguage{javascript}{
+        sensitive=true,
+        morekeywords={async, await, break, case, catch, class, const, continue, debugger,
+            default, delete, do, else, export, extends, false, finally, for, from, function, if,
+            import, in, instanceof, let, new, null, of, return, super, switch, this, throw,
+            true, try, typeof, undefined, var, void, while, with, yield},
+        morecomment=[l]{//},
+        morecomment=[s]{/*}{*/},
+        morestring=[b]",
+        morestring=[b]',
+        morestring=[b]`,
+    }"#,
+    ),
+    (
+        "typescript",
+        r#"
+    \lstdefinelanguage{typescript}{
+        sensitive=true,
+        morekeywords={abstract, as, async, await, break, case, catch, class, const, continue,
+            declare, default, delete, do, else, enum, export, extends, false, finally, for,
+            from, function, if, implements, import, in, instanceof, interface, keyof, let,
+            namespace, new, null, of, private, protected, public, readonly, return, super,
+            switch, this, throw, true, try, type, typeof, undefined, var, void, while, yield},
+        morekeywords=[2]{any, boolean, never, number, object, string, symbol, unknown},
+        morecomment=[l]{//},
+        morecomment=[s]{/*}{*/},
+        morestring=[b]",
+        morestring=[b]',
+        morestring=[b]`,
+    }"#,
+    ),
+    (
+        "json",
+        r#"
+    \lstdefinelanguage{json}{
+        sensitive=true,
+        morekeywords={true, false, null},
+        morestring=[b]",
+    }"#,
+    ),
+    (
+        "yaml",
+        r#"
+    \lstdefinelanguage{yaml}{
+        sensitive=false,
+        morekeywords={true, false, null, yes, no, on, off},
+        morecomment=[l]{\#},
+        morestring=[b]",
+        morestring=[b]',
+    }"#,
+    ),
+    (
+        "toml",
+        r#"
+    \lstdefinelanguage{toml}{
+        sensitive=true,
+        morekeywords={true, false},
+        morecomment=[l]{\#},
+        morestring=[b]",
+        morestring=[b]',
+    }"#,
+    ),
+    (
+        "kotlin",
+        r#"
+    \lstdefinelanguage{kotlin}{
+        sensitive=true,
+        morekeywords={as, break, class, companion, continue, data, do, else, enum, false, for,
+            fun, if, import, in, interface, internal, is, lateinit, null, object, open,
+            override, package, private, protected, public, return, sealed, super, this, throw,
+            true, try, typealias, val, var, when, while},
+        morekeywords=[2]{Any, Boolean, Byte, Char, Double, Float, Int, List, Long, Map, Set,
+            Short, String, Unit},
+        morecomment=[l]{//},
+        morecomment=[s]{/*}{*/},
+        morestring=[b]",
+    }"#,
+    ),
+    (
+        "dockerfile",
+        r#"
+    \lstdefinelanguage{dockerfile}{
+        sensitive=false,
+        morekeywords={ADD, ARG, AS, CMD, COPY, ENTRYPOINT, ENV, EXPOSE, FROM, HEALTHCHECK, LABEL,
+            MAINTAINER, ONBUILD, RUN, SHELL, STOPSIGNAL, USER, VOLUME, WORKDIR},
+        morecomment=[l]{\#},
+        morestring=[b]",
+    }"#,
+    ),
+];
+
+pub const LISTINGS_LANGUAGES: [&str; 94] = [
     "python",
     "c",
     "matlab",
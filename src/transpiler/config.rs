@@ -0,0 +1,141 @@
+//! Settings read from a JSON configuration file.
+//!
+//! ```
+//! use saurus::transpiler::code_blocks::Fallback;
+//! use saurus::transpiler::config::Config;
+//! use saurus::Options;
+//!
+//! let config = Config::from_json(r#"{ "fallback_language": "none", "languages": { "rust": "c" } }"#).unwrap();
+//! let mut options = Options::default();
+//! config.apply(&mut options);
+//! assert_eq!(options.languages.fallback, Fallback::None);
+//! assert_eq!(options.languages.resolve("rust"), Ok("c".to_string()));
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::transpiler::code_blocks::{self, CodeBackend, Fallback};
+use crate::transpiler::highlight;
+use crate::transpiler::styles::{self, ListingStyle};
+use crate::transpiler::{Error, Options};
+
+/// The file saurus looks for next to the document it reads
+pub const FILE_NAME: &str = "saurus.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// What code blocks get when listings does not know their language:
+    /// `"none"`, `"error"` or a listings language
+    pub fallback_language: Option<Fallback>,
+    /// Code block languages to write as another listings language
+    pub languages: HashMap<String, String>,
+    /// `"listings"`, `"minted"`, `"verbatim"`, `"fancyvrb"` or `"builtin"`
+    pub code_backend: Option<CodeBackend>,
+    /// The bundled theme the `builtin` code backend colors code with
+    pub code_theme: Option<String>,
+    /// The style listings are written in unless they choose another:
+    /// `"light"`, `"dark"`, `"print"`, `"solarized"` or one of `listing_styles`
+    pub listing_style: Option<String>,
+    /// Listing styles to define, by name
+    pub listing_styles: HashMap<String, ListingStyle>,
+}
+
+impl Config {
+    pub fn from_json(json: &str) -> Result<Config, Error> {
+        let config: Config = serde_json::from_str(json)
+            .map_err(|error| Error::Config(format!("invalid configuration: {}", error)))?;
+
+        let fallback = match &config.fallback_language {
+            Some(Fallback::Language(language)) => Some(language),
+            _ => None,
+        };
+        for language in config.languages.values().chain(fallback) {
+            if code_blocks::is_invalid_language(code_blocks::alias(language)) {
+                return Err(Error::Config(format!(
+                    "invalid configuration: language \"{}\" is not supported by listings",
+                    language
+                )));
+            }
+        }
+        if let Some(theme) = &config.code_theme {
+            if !highlight::is_theme(theme) {
+                return Err(Error::Config(format!(
+                    "invalid configuration: there is no code theme \"{}\"",
+                    theme
+                )));
+            }
+        }
+        for (name, style) in config.listing_styles.iter() {
+            let invalid = |message: String| {
+                Err(Error::Config(format!(
+                    "invalid configuration: listing style \"{}\" {}",
+                    name, message
+                )))
+            };
+            if !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return invalid("must be named with letters, digits, `-` and `_`".to_string());
+            }
+            for (role, color) in style.colors() {
+                match color {
+                    Some(color) if !styles::is_color(color) => {
+                        return invalid(format!(
+                            "has {} \"{}\", which is not a hex color like \"F2F2EB\"",
+                            role, color
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+            if styles::find(name, &config.listing_styles).is_none() {
+                return invalid(format!(
+                    "is based on \"{}\", which is not a style or is based on it in turn",
+                    style.base.as_deref().unwrap_or_default()
+                ));
+            }
+        }
+        if let Some(style) = &config.listing_style {
+            if styles::find(style, &config.listing_styles).is_none() {
+                return Err(Error::Config(format!(
+                    "invalid configuration: there is no listing style \"{}\"",
+                    style
+                )));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Reads the configuration file at `path`.
+    pub fn load(path: &Path) -> Result<Config, Error> {
+        let json = fs::read_to_string(path).map_err(|error| {
+            Error::Config(format!("unable to read {}: {}", path.display(), error))
+        })?;
+        Config::from_json(&json)
+            .map_err(|error| Error::Config(format!("{}: {}", path.display(), error)))
+    }
+
+    /// Overrides the settings of `options` that this configuration sets.
+    pub fn apply(&self, options: &mut Options) {
+        if let Some(fallback) = &self.fallback_language {
+            options.languages.fallback = fallback.clone();
+        }
+        options.languages.map.extend(self.languages.clone());
+        if let Some(code_backend) = self.code_backend {
+            options.code_backend = code_backend;
+        }
+        if let Some(theme) = &self.code_theme {
+            options.code_theme = Some(theme.clone());
+        }
+        if let Some(style) = &self.listing_style {
+            options.listing_style = Some(style.clone());
+        }
+        options.listing_styles.extend(self.listing_styles.clone());
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found while transpiling, located by its 1-based line and column
+/// in the Markdown source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, line: usize, column: usize, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            line,
+            column,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn warning(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Diagnostic::new(Severity::Warning, line, column, message)
+    }
+
+    pub fn error(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Diagnostic::new(Severity::Error, line, column, message)
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Renders the diagnostic along with the offending line of `source`.
+    ///
+    /// ```
+    /// use saurus::transpiler::diagnostics::Diagnostic;
+    ///
+    /// let diagnostic = Diagnostic::warning(2, 3, "missing space").with_hint("add a space");
+    /// let expected = "\
+    /// warning: missing space
+    ///  --> notes.md:2:3
+    ///   |
+    /// 2 | ##Heading
+    ///   |   ^
+    ///   = hint: add a space
+    /// ";
+    /// assert_eq!(diagnostic.render("Text\n##Heading\n", "notes.md"), expected);
+    /// ```
+    pub fn render(&self, source: &str, file_name: &str) -> String {
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let mut rendered = format!(
+            "{}: {}\n{}--> {}",
+            self.severity, self.message, gutter, file_name
+        );
+        // Diagnostics about documents read from elsewhere have no location
+        if self.line > 0 {
+            rendered.push_str(&format!(":{}:{}", self.line, self.column));
+        }
+        rendered.push('\n');
+        if let Some(text) = source.lines().nth(self.line.wrapping_sub(1)) {
+            rendered.push_str(&format!("{} |\n", gutter));
+            rendered.push_str(&format!("{} | {}\n", number, text));
+            rendered.push_str(&format!(
+                "{} | {}^\n",
+                gutter,
+                " ".repeat(self.column.saturating_sub(1))
+            ));
+        }
+        if let Some(hint) = &self.hint {
+            rendered.push_str(&format!("{} = hint: {}\n", gutter, hint));
+        }
+        rendered
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, self.severity, self.message
+        )
+    }
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+}
+
+/// Turns every warning into an error, for runs that must be clean.
+///
+/// [`Options::strict`](crate::Options::strict) does this for a whole run:
+///
+/// ```
+/// use saurus::{transpile_output, Error, Options};
+///
+/// let input = "Text\n-dropped\n";
+/// assert!(transpile_output(input, &Options::default()).is_ok());
+/// let strict = Options { strict: true, ..Options::default() };
+/// match transpile_output(input, &strict) {
+///     Err(Error::Diagnostics(diagnostics)) => assert_eq!(diagnostics[0].message, "line is ignored"),
+///     result => panic!("expected an error, found {:?}", result),
+/// }
+/// ```
+pub fn deny_warnings(diagnostics: &mut [Diagnostic]) {
+    for diagnostic in diagnostics.iter_mut() {
+        diagnostic.severity = Severity::Error;
+    }
+}
@@ -0,0 +1,102 @@
+//! External programs run on the document tree as passes.
+//!
+//! A filter reads the document as pandoc JSON on stdin and writes the
+//! transformed document to stdout, exactly like a pandoc JSON filter. Like
+//! pandoc, saurus passes the name of the output format as its argument.
+//! Each block's Markdown lines are sent in a `data-pos` attribute, so blocks
+//! the filter keeps its attributes on, or its `Div` around, keep their spans.
+//!
+//! ```no_run
+//! use saurus::transpiler::filter::Filter;
+//! use saurus::transpiler::{transpile_with_passes, Options, Pass};
+//!
+//! let options = Options::default();
+//! let mut passes: Vec<Box<dyn Pass>> = vec![Box::new(Filter::new("./smallcaps.py", options.target))];
+//! let output = transpile_with_passes("# Title", &options, &mut passes).unwrap();
+//! ```
+
+use std::ffi::OsString;
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::transpiler::ast::Document;
+use crate::transpiler::diagnostics::{Diagnostic, Severity};
+use crate::transpiler::{pandoc, Error, Pass, Target};
+
+#[derive(Debug, Clone)]
+pub struct Filter {
+    program: PathBuf,
+    args: Vec<OsString>,
+    /// Warnings about the document the filter returned
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Filter {
+    /// A filter running `program` with the name of `target` as its argument
+    pub fn new(program: impl Into<PathBuf>, target: Target) -> Self {
+        Filter {
+            program: program.into(),
+            args: vec![target.name().into()],
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> Error {
+        Error::Pass(format!("filter {}: {}", self.program.display(), message))
+    }
+}
+
+impl Pass for Filter {
+    fn run(&mut self, document: &mut Document) -> Result<(), Error> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|error| self.error(format!("unable to run: {}", error)))?;
+
+        // Written from another thread so a filter streaming its output back
+        // cannot block on a full pipe while saurus is still writing
+        let json = pandoc::to_json_with_spans(document);
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let writer = thread::spawn(move || stdin.write_all(json.as_bytes()));
+        let output = child
+            .wait_with_output()
+            .map_err(|error| self.error(error))?;
+        let written = writer.join().expect("the writing thread does not panic");
+
+        if !output.status.success() {
+            return Err(self.error(format!("exited with {}", output.status)));
+        }
+        // A filter may well make its document without reading saurus's
+        match written {
+            Err(error) if error.kind() != ErrorKind::BrokenPipe => {
+                return Err(self.error(format!("unable to write the document: {}", error)));
+            }
+            _ => {}
+        }
+
+        let json = String::from_utf8(output.stdout)
+            .map_err(|_| self.error("returned output that is not UTF-8"))?;
+        let mut diagnostics = Vec::new();
+        let filtered = pandoc::from_json(&json, &mut diagnostics)
+            .map_err(|error| self.error(format!("returned an invalid tree: {}", error)))?;
+        if let Some(error) = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            return Err(self.error(format!("returned an invalid tree: {}", error.message)));
+        }
+        // Elements saurus drops are only warned about, as with `--from pandoc-json`
+        self.diagnostics = diagnostics;
+        *document = filtered;
+        Ok(())
+    }
+
+    fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
@@ -1,127 +1,674 @@
-use crate::transpiler::{lexer, parser, re};
-
-pub fn generate_line(mut contents: parser::Contents) -> Option<String> {
-    type Token = lexer::Token;
-    if let None = contents.line {
-        match contents.kind {
-            Token::FileStart => Some(format!("\\begin{{document}}\n {}", qol_customizations())),
-            Token::FileEnd => Some(format!("\\end{{document}}")),
-            Token::OrderedList(_) => Some(format!(
-                "{}\\end{{enumerate}}",
-                indent(contents.indent_level)
-            )),
-            Token::UnorderedList => {
-                Some(format!("{}\\end{{itemize}}", indent(contents.indent_level)))
+use std::collections::HashMap;
+
+use crate::transpiler::ast::{CodeAttributes, CodeBlock, Heading, List, ListKind, Quote};
+use crate::transpiler::backend::{Backend, Context, Fragment, Resource, Resources};
+use crate::transpiler::code_blocks::{self, CodeBackend, Fallback};
+use crate::transpiler::diagnostics::Diagnostic;
+use crate::transpiler::highlight;
+use crate::transpiler::lexer::Span;
+use crate::transpiler::styles::{self, ListingStyle};
+use crate::transpiler::Options;
+
+pub fn heading<B: Backend + ?Sized>(
+    backend: &mut B,
+    heading: &Heading,
+    cx: &mut Context,
+) -> Vec<Fragment> {
+    let line = backend.inlines(&heading.content, cx);
+    let tex = match heading.level {
+        1 => format!("\\section{{{}}}", line),
+        2 => format!("\\subsection{{{}}}", line),
+        3 => format!("\\subsubsection{{{}}}", line),
+        level => {
+            cx.diagnostics.push(
+                Diagnostic::warning(
+                    heading.span.start,
+                    1,
+                    format!("heading level {} is deeper than LaTeX supports", level),
+                )
+                .with_hint("rendered as \\subsubsection"),
+            );
+            format!("\\subsubsection{{{}}}", line)
+        }
+    };
+    vec![Fragment::new(tex, heading.span)]
+}
+
+pub fn indent(indent_level: usize) -> String {
+    "    ".repeat(indent_level)
+}
+
+/// Escapes the characters that would otherwise be read as LaTeX markup.
+/// Raw LaTeX commands are kept as written along with their arguments, so
+/// `\ref{fig_a}` still works.
+///
+/// ```
+/// use saurus::transpiler::generator;
+/// assert_eq!(generator::escape("You & Me"), r"You \& Me");
+/// assert_eq!(generator::escape("$123 or 50% of #1_a"), r"\$123 or 50\% of \#1\_a");
+/// assert_eq!(generator::escape("a → b"), r"a $\rightarrow$ b");
+/// assert_eq!(generator::escape(r"See \ref{fig_a} & \cite[p.~3]{knuth_84}"), r"See \ref{fig_a} \& \cite[p.~3]{knuth_84}");
+/// assert_eq!(generator::escape(r"a \\ b_c"), r"a \\ b\_c");
+/// ```
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '\\' {
+            let command = raw_command(rest);
+            escaped.push_str(&rest[..command]);
+            rest = &rest[command..];
+            continue;
+        }
+        match c {
+            '&' | '$' | '%' | '#' | '_' => {
+                escaped.push('\\');
+                escaped.push(c);
             }
-            Token::Blank => Some("".to_string()), // blank line
-            _ => None,
+            '→' => escaped.push_str("$\\rightarrow$"),
+            _ => escaped.push(c),
         }
-    } else {
-        // Can unwrap since line is not None
-        let line = transpile_line(&mut contents.line).unwrap();
-        match contents.kind {
-            Token::Heading(level) => match level {
-                1 => Some(format!("\\section{{{}}}\n", line)),
-                2 => Some(format!("\\subsection{{{}}}\n", line)),
-                3 => Some(format!("\\subsubsection{{{}}}\n", line)),
-                _ => Some(format!("\\subsubsection{{{}}}\n", line)),
-            },
-            Token::UnorderedList | Token::OrderedList(_) => Some(listify(contents)),
-            Token::Text => Some(line),
-            Token::CodeBlock => Some(code_block(contents)),
-            Token::BlockQuote => Some(block_quote(&mut contents)),
-            _ => None,
+        rest = &rest[c.len_utf8()..];
+    }
+    escaped
+}
+
+/// The length of the LaTeX command `text` starts with, including the
+/// bracketed and braced arguments right after it
+fn raw_command(text: &str) -> usize {
+    let name = text[1..]
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(text.len() - 1);
+    if name == 0 {
+        // A control symbol such as `\\`
+        return 1 + text[1..].chars().next().map_or(0, char::len_utf8);
+    }
+    let mut length = 1 + name;
+    while let Some(open) = text[length..].chars().next() {
+        let close = match open {
+            '{' => '}',
+            '[' => ']',
+            _ => break,
+        };
+        let mut depth = 0;
+        let end = text[length..].char_indices().find_map(|(index, c)| {
+            match c {
+                _ if c == open => depth += 1,
+                _ if c == close => depth -= 1,
+                _ => {}
+            }
+            (depth == 0).then_some(index)
+        });
+        match end {
+            Some(end) => length += end + 1,
+            // An unclosed argument is text like any other
+            None => break,
         }
     }
+    length
 }
 
-pub fn indent(indent_level: usize) -> String {
-    return "    ".repeat(indent_level);
-}
-
-fn transpile_line(line: &mut Option<String>) -> Option<String> {
-    if let Some(line) = line {
-        *line = re::bold(line);
-        *line = re::italicize(line);
-        *line = re::inline_code(line);
-        *line = re::strike_out(line);
-        *line = re::symbols(line);
-        *line = re::links(line);
-        Some(line.to_string())
-    } else {
-        None
-    }
-}
-
-fn block_quote(contents: &mut parser::Contents) -> String {
-    // Can unwrap since any group item will not be None per Parser's
-    // design
-    let line = re::replace_block_quote(&contents.line.as_ref().unwrap());
-    type Chronology = parser::Chronology;
-    match contents.chron {
-        Chronology::Start => {
-            format!("\\begin{{quote}}\n    {}\\\\", line)
-        }
-        Chronology::Middle => format!("    {}\\\\", line),
-        Chronology::End => format!("    {}\n\\end{{quote}}\n", line),
-        Chronology::None => format!("\\begin{{quote}}\n    {}\n\\end{{quote}}\n", line),
-    }
-}
-
-fn listify(contents: parser::Contents) -> String {
-    type Chronology = parser::Chronology;
-    type Token = lexer::Token;
-    // Can unwrap since any group item will not be None per Parser's
-    // design
-    let line = contents.line.unwrap();
-    if let Token::UnorderedList = contents.kind {
-        let indent = indent(contents.indent_level);
-        match contents.chron {
-            Chronology::Start => {
-                format!(
-                    "{}\\begin{{itemize}}\n    {}\\item {}",
-                    indent, indent, line
-                )
+/// `\verb` with a delimiter that does not occur in `code`
+pub fn verb(code: &str) -> String {
+    let delimiter = ['|', '!', '+', '@', '=', '/']
+        .into_iter()
+        .find(|delimiter| !code.contains(*delimiter))
+        .unwrap_or('|');
+    format!("\\verb{}{}{}", delimiter, code, delimiter)
+}
+
+pub fn block_quote<B: Backend + ?Sized>(
+    backend: &mut B,
+    quote: &Quote,
+    cx: &mut Context,
+) -> Vec<Fragment> {
+    let mut body = Vec::new();
+    for block in quote.blocks.iter() {
+        let tex = backend
+            .block(block, cx)
+            .into_iter()
+            .map(|fragment| fragment.text)
+            .collect::<Vec<_>>()
+            .join("\n");
+        body.push(indent_lines(&tex, &indent(1)));
+    }
+    let tex = format!("\\begin{{quote}}\n{}\n\\end{{quote}}", body.join("\n\n"));
+    vec![Fragment::new(tex, quote.span)]
+}
+
+/// Counters numbering each level of nested `enumerate`s
+const ENUM_COUNTERS: [&str; 4] = ["enumi", "enumii", "enumiii", "enumiv"];
+
+pub fn listify<B: Backend + ?Sized>(
+    backend: &mut B,
+    list: &List,
+    cx: &mut Context,
+) -> Vec<Fragment> {
+    let depth = cx.depth;
+    let indent = indent(depth);
+    let mut fragments = Vec::new();
+    for item in list.items.iter() {
+        let line = backend.inlines(&item.content, cx);
+        fragments.push(Fragment::new(
+            format!("{}    \\item {}", indent, line),
+            Span::line(item.span.start),
+        ));
+        cx.depth += 1;
+        for child in item.children.iter() {
+            fragments.extend(backend.block(child, cx));
+        }
+        cx.depth -= 1;
+    }
+
+    let (environment, counter) = match list.kind {
+        ListKind::Unordered => ("itemize", None),
+        ListKind::Ordered { start } => {
+            let counter = ENUM_COUNTERS[depth.min(ENUM_COUNTERS.len() - 1)];
+            ("enumerate", Some((counter, start.saturating_sub(1))))
+        }
+    };
+    let mut begin = format!("{}\\begin{{{}}}\n", indent, environment);
+    if let Some((counter, value)) = counter {
+        begin.push_str(&format!(
+            "{}    \\setcounter{{{}}}{{{}}}\n",
+            indent, counter, value
+        ));
+    }
+    let end = format!("\n{}\\end{{{}}}", indent, environment);
+
+    // The environment's delimiters share lines with its first and last items
+    match (fragments.first_mut(), list.items.is_empty()) {
+        (Some(first), false) => first.text.insert_str(0, &begin),
+        _ => fragments.push(Fragment::new(begin.trim_end().to_string(), list.span)),
+    }
+    if let Some(last) = fragments.last_mut() {
+        last.text.push_str(&end);
+    }
+    fragments
+}
+
+/// Starts each highlighted line of a listing, escaping to LaTeX to draw the
+/// highlight behind it
+pub const HIGHLIGHT: &str = "(*@\\highlightedline@*)";
+
+/// A code block as a listing. Its language, kept as written in the tree, is
+/// turned into a listings language here, so only listings reports languages
+/// it does not know.
+///
+/// ```
+/// use saurus::transpiler::ast::Block;
+/// use saurus::transpiler::code_blocks::{Fallback, Languages};
+/// use saurus::transpiler::{generate, parse, Options};
+///
+/// let document = parse("```zig\nconst x = 1;\n```", &mut Vec::new());
+/// match &document.blocks[0] {
+///     Block::CodeBlock(code_block) => assert_eq!(code_block.language.as_deref(), Some("zig")),
+///     block => panic!("expected a code block, found {:?}", block),
+/// }
+///
+/// let languages = Languages { fallback: Fallback::None, ..Languages::default() };
+/// let options = Options { languages, ..Options::default() };
+/// let mut diagnostics = Vec::new();
+/// let (tex, _) = generate(&document, &options, &mut diagnostics);
+/// assert!(tex.contains("\\begin{lstlisting}[style=light]"));
+/// assert_eq!(diagnostics[0].message, "language \"zig\" is not supported by listings");
+/// ```
+///
+/// Every line between the fences is code, blank lines at the end too:
+///
+/// ```
+/// use saurus::transpiler::ast::Block;
+/// use saurus::transpiler::{generate, parse, Options};
+///
+/// let document = parse("```python\nx = 1\n\n```\n\nafter", &mut Vec::new());
+/// match &document.blocks[0] {
+///     Block::CodeBlock(code_block) => assert_eq!(code_block.code, "x = 1\n"),
+///     block => panic!("expected a code block, found {:?}", block),
+/// }
+/// let (tex, _) = generate(&document, &Options::default(), &mut Vec::new());
+/// assert!(tex.contains("x = 1\n\n\\end{lstlisting}"));
+/// ```
+pub fn code_block(code: &CodeBlock, cx: &mut Context) -> Vec<Fragment> {
+    let attributes = &code.attributes;
+    let mut options = Vec::new();
+    if let Some(language) = listings_language(code, cx) {
+        options.push(format!("language={}", language));
+        cx.resources.require(Resource::Language(language));
+    }
+    let style = listing_style(code, cx);
+    options.push(format!("style={}", style));
+    cx.resources.require(Resource::ListingStyle(style));
+    if let Some(caption) = &attributes.caption {
+        options.push(format!("caption={{{}}}", escape(caption)));
+    }
+    if let Some(label) = &attributes.label {
+        options.push(format!("label={{{}}}", label));
+    }
+    if attributes.line_numbers || attributes.first_line.is_some() {
+        options.push("numbers=left".to_string());
+    }
+    if let Some(first_line) = attributes.first_line {
+        options.push(format!("firstnumber={}", first_line));
+    }
+    // `lines` would drop a blank last line
+    let mut lines: Vec<String> = code.code.split('\n').map(str::to_string).collect();
+    if !attributes.highlight.is_empty() {
+        options.push("escapeinside={(*@}{@*)}".to_string());
+        for (index, line) in lines.iter_mut().enumerate() {
+            if attributes.is_highlighted(attributes.first_line() + index) {
+                line.insert_str(0, HIGHLIGHT);
             }
-            Chronology::Middle => format!("{}    \\item {}", indent, line),
-            Chronology::End => format!(
-                "{}    \\item {}\n{}\\end{{itemize}}\n",
-                indent, line, indent
-            ),
-            Chronology::None => format!(
-                "{}\\begin{{itemize}}\n    {}\\item {}\n{}\\end{{itemize}}\n",
-                indent, indent, line, indent,
-            ),
-        }
-    } else if let Token::OrderedList(num) = contents.kind {
-        match contents.chron {
-            Chronology::Start => {
-                format!(
-                    "\\begin{{enumerate}}\n    \\setcounter{{enumi}}{{{}}}\n    \\item {}",
-                num - 1, line
-                )
-            },
-            Chronology::Middle => format!("    \\item {}", line),
-            Chronology::End => format!("    \\item {}\n\\end{{enumerate}}\n", line),
-            Chronology::None => format!("\\begin{{enumerate}}\n    \\setcounter{{enumi}}{{{}}}\n    \\item {}\n\\end{{enumerate}}\n", num - 1, line),
         }
+    }
+
+    if let Some(file) = &attributes.file {
+        if let Some((first, last)) = attributes.lines {
+            options.push(format!("firstline={}, lastline={}", first, last));
+        }
+        let tex = format!("\\lstinputlisting[{}]{{{}}}", options.join(", "), file);
+        return vec![Fragment::new(tex, code.span)];
+    }
+
+    let options = options.join(", ");
+    let tex = if code.code.is_empty() {
+        format!("\\begin{{lstlisting}}[{}]\n\\end{{lstlisting}}", options)
     } else {
-        "".to_string()
+        format!(
+            "\\begin{{lstlisting}}[{}]\n{}\n\\end{{lstlisting}}",
+            options,
+            lines.join("\n")
+        )
+    };
+    vec![Fragment::new(tex, code.span)]
+}
+
+/// The listings language of `code`, reporting languages listings does not
+/// know and writing them as the configured fallback.
+fn listings_language(code: &CodeBlock, cx: &mut Context) -> Option<String> {
+    let languages = &cx.options.languages;
+    let unknown = match languages.resolve(code.language.as_deref()?) {
+        Ok(language) => return Some(language),
+        Err(unknown) => unknown,
+    };
+    let line = code.span.start;
+    let message = format!("language \"{}\" is not supported by listings", unknown);
+    let (diagnostic, language) = match &languages.fallback {
+        Fallback::Language(fallback) => (
+            Diagnostic::warning(line, 1, message)
+                .with_hint(format!("using \"{}\" instead", fallback)),
+            Some(fallback.clone()),
+        ),
+        Fallback::None => (
+            Diagnostic::warning(line, 1, message).with_hint("writing it as a plain listing"),
+            None,
+        ),
+        Fallback::Error => (
+            Diagnostic::error(line, 1, message)
+                .with_hint("map it to a supported language in the configuration"),
+            None,
+        ),
+    };
+    cx.diagnostics.push(diagnostic);
+    language
+}
+
+/// The listing style of `code`, or the document's if it has no such style
+fn listing_style(code: &CodeBlock, cx: &mut Context) -> String {
+    let options = cx.options;
+    let document = match styles::find(options.listing_style(), &options.listing_styles) {
+        Some(_) => options.listing_style(),
+        None => styles::DEFAULT_STYLE,
+    };
+    let Some(style) = &code.attributes.style else {
+        return document.to_string();
+    };
+    if styles::find(style, &options.listing_styles).is_none() {
+        let mut known: Vec<&str> = styles::BUILTIN.to_vec();
+        known.extend(options.listing_styles.keys().map(String::as_str));
+        cx.diagnostics.push(
+            Diagnostic::warning(
+                code.span.start,
+                1,
+                format!("unknown listing style `{}`", style),
+            )
+            .with_hint(format!(
+                "using {} instead; the styles are {}",
+                document,
+                known.join(", ")
+            )),
+        );
+        return document.to_string();
     }
+    style.clone()
 }
 
-fn code_block(content: parser::Contents) -> String {
-    type Chronology = parser::Chronology;
-    match content.chron {
-        Chronology::Start => {
+/// A code block for minted, with its language, as written, as a Pygments
+/// lexer. Languages listings does not know are fine here, so they are not
+/// reported.
+pub fn minted(code: &CodeBlock, cx: &mut Context) -> Vec<Fragment> {
+    let attributes = &code.attributes;
+    unsupported_attributes(code, CodeBackend::Minted, cx);
+    let lexer = code
+        .language
+        .as_deref()
+        .map_or("text", code_blocks::pygments_lexer);
+    let mut options = Vec::new();
+    if attributes.line_numbers || attributes.first_line.is_some() {
+        options.push("linenos".to_string());
+    }
+    options.extend(first_number(attributes));
+    if !attributes.highlight.is_empty() {
+        options.push(format!(
+            "highlightlines={{{}}}",
+            code_blocks::ranges_string(&attributes.highlight)
+        ));
+    }
+    let mut tex = match &attributes.file {
+        Some(file) => {
+            options.extend(line_range(attributes));
             format!(
-                "\\begin{{lstlisting}}[language={}, style=myStyle]",
-                content.line.unwrap()
+                "\\inputminted{}{{{}}}{{{}}}",
+                bracketed(&options),
+                lexer,
+                file
             )
         }
-        Chronology::End => "\\end{lstlisting}\n".to_owned(),
-        _ => "".to_string(),
+        None => verbatim_environment(
+            &format!("\\begin{{minted}}{}{{{}}}", bracketed(&options), lexer),
+            &code.code,
+            "\\end{minted}",
+        ),
+    };
+    if attributes.caption.is_some() || attributes.label.is_some() {
+        let mut float = format!("\\begin{{listing}}[H]\n{}\n", tex);
+        if let Some(caption) = &attributes.caption {
+            float.push_str(&format!("\\caption{{{}}}\n", escape(caption)));
+        }
+        if let Some(label) = &attributes.label {
+            float.push_str(&format!("\\label{{{}}}\n", label));
+        }
+        float.push_str("\\end{listing}");
+        tex = float;
     }
+    vec![Fragment::new(tex, code.span)]
+}
+
+/// A code block in fancyvrb's `Verbatim`, captioned by a label in its frame.
+pub fn fancyvrb(code: &CodeBlock, cx: &mut Context) -> Vec<Fragment> {
+    let attributes = &code.attributes;
+    unsupported_attributes(code, CodeBackend::Fancyvrb, cx);
+    let mut options = fancyvrb_options(attributes);
+    let tex = match &attributes.file {
+        Some(file) => {
+            options.extend(line_range(attributes));
+            format!("\\VerbatimInput{}{{{}}}", bracketed(&options), file)
+        }
+        None => verbatim_environment(
+            &format!("\\begin{{Verbatim}}{}", bracketed(&options)),
+            &code.code,
+            "\\end{Verbatim}",
+        ),
+    };
+    vec![Fragment::new(tex, code.span)]
+}
+
+/// A code block colored with [`highlight`], in fancyvrb's `Verbatim` on the
+/// theme's background. Languages without a grammar are written as plain
+/// text.
+///
+/// ```
+/// use saurus::transpiler::code_blocks::CodeBackend;
+/// use saurus::transpiler::{generate, parse, Options};
+///
+/// let document = parse("```zig\nconst x = 1;\n```", &mut Vec::new());
+/// let options = Options { code_backend: CodeBackend::Builtin, ..Options::default() };
+/// let mut diagnostics = Vec::new();
+/// let (tex, _) = generate(&document, &options, &mut diagnostics);
+/// assert!(tex.contains("\nconst x = 1;\n"));
+/// assert!(tex.contains(r"\begin{snugshade}"));
+/// // The color's `]` must not end the `Verbatim`'s options
+/// assert!(tex.contains(r"formatcom={\color[HTML]{323232}}]"));
+/// assert_eq!(diagnostics[0].message, "no grammar is bundled for language \"zig\"");
+/// ```
+pub fn builtin(code: &CodeBlock, cx: &mut Context) -> Vec<Fragment> {
+    unsupported_attributes(code, CodeBackend::Builtin, cx);
+    let mut theme = cx
+        .options
+        .code_theme
+        .as_deref()
+        .unwrap_or(highlight::DEFAULT_THEME);
+    if !highlight::is_theme(theme) {
+        let themes: Vec<_> = highlight::theme_names().collect();
+        cx.diagnostics.push(
+            Diagnostic::warning(
+                code.span.start,
+                1,
+                format!("unknown code theme `{}`", theme),
+            )
+            .with_hint(format!(
+                "using {} instead; the themes are {}",
+                highlight::DEFAULT_THEME,
+                themes.join(", ")
+            )),
+        );
+        theme = highlight::DEFAULT_THEME;
+    }
+    if let Some(language) = &code.language {
+        if !highlight::has_grammar(language) {
+            cx.diagnostics.push(
+                Diagnostic::warning(
+                    code.span.start,
+                    1,
+                    format!("no grammar is bundled for language \"{}\"", language),
+                )
+                .with_hint("writing it as plain text"),
+            );
+        }
+    }
+    let highlighted = highlight::highlight(&code.code, code.language.as_deref(), theme)
+        .expect("the default theme is bundled");
+
+    let mut options = fancyvrb_options(&code.attributes);
+    options.push("commandchars=\\\\\\{\\}".to_string());
+    options.push(format!(
+        "formatcom={{\\color[HTML]{{{}}}}}",
+        highlighted.foreground
+    ));
+    let verbatim = verbatim_environment(
+        &format!("\\begin{{Verbatim}}{}", bracketed(&options)),
+        &highlighted.lines.join("\n"),
+        "\\end{Verbatim}",
+    );
+    let tex = format!(
+        "\\begin{{highlighted}}{{{}}}\n{}\n\\end{{highlighted}}",
+        highlighted.background, verbatim
+    );
+    vec![Fragment::new(tex, code.span)]
+}
+
+/// A code block in LaTeX's own `verbatim`, which takes no options at all.
+pub fn verbatim(code: &CodeBlock, cx: &mut Context) -> Vec<Fragment> {
+    unsupported_attributes(code, CodeBackend::Verbatim, cx);
+    let tex = verbatim_environment("\\begin{verbatim}", &code.code, "\\end{verbatim}");
+    vec![Fragment::new(tex, code.span)]
+}
+
+/// Warns about the attributes of `code` that `backend` cannot show.
+fn unsupported_attributes(code: &CodeBlock, backend: CodeBackend, cx: &mut Context) {
+    let attributes = &code.attributes;
+    if let Some(style) = &attributes.style {
+        cx.diagnostics.push(
+            Diagnostic::warning(
+                code.span.start,
+                1,
+                format!("{} has no listing style `{}`", backend.name(), style),
+            )
+            .with_hint("it is ignored; only the listings code backend has listing styles"),
+        );
+    }
+    // minted shows everything else
+    if backend == CodeBackend::Minted {
+        return;
+    }
+    let mut unsupported = Vec::new();
+    if !attributes.highlight.is_empty() {
+        unsupported.push("highlight lines");
+    }
+    if attributes.label.is_some() {
+        unsupported.push("label listings");
+    }
+    if backend == CodeBackend::Verbatim {
+        if attributes.caption.is_some() {
+            unsupported.push("caption listings");
+        }
+        if attributes.line_numbers || attributes.first_line.is_some() {
+            unsupported.push("number lines");
+        }
+    }
+    if let Some((last, rest)) = unsupported.split_last() {
+        let what = match rest {
+            [] => last.to_string(),
+            _ => format!("{} or {}", rest.join(", "), last),
+        };
+        cx.diagnostics.push(
+            Diagnostic::warning(
+                code.span.start,
+                1,
+                format!("{} cannot {}", backend.name(), what),
+            )
+            .with_hint("it is ignored; the listings and minted code backends support it"),
+        );
+    }
+}
+
+/// The options of a fancyvrb `Verbatim` for `attributes`
+fn fancyvrb_options(attributes: &CodeAttributes) -> Vec<String> {
+    let mut options = Vec::new();
+    if attributes.line_numbers || attributes.first_line.is_some() {
+        options.push("numbers=left".to_string());
+    }
+    options.extend(first_number(attributes));
+    if let Some(caption) = &attributes.caption {
+        options.push(format!("frame=topline, label={{{}}}", escape(caption)));
+    }
+    options
+}
+
+/// `firstnumber` for the first line's number, which for part of a file
+/// is the number of that line in the file
+fn first_number(attributes: &CodeAttributes) -> Option<String> {
+    let numbered = attributes.line_numbers || attributes.first_line.is_some();
+    let first_line = match (attributes.first_line, attributes.lines) {
+        (Some(first_line), _) => first_line,
+        (None, Some((first, _))) if numbered && attributes.file.is_some() => first,
+        _ => return None,
+    };
+    Some(format!("firstnumber={}", first_line))
+}
+
+/// `firstline` and `lastline` for the lines of a file a code block lists
+fn line_range(attributes: &CodeAttributes) -> Option<String> {
+    let (first, last) = attributes.lines?;
+    Some(format!("firstline={}, lastline={}", first, last))
+}
+
+/// `options` in brackets, or nothing when there are none
+fn bracketed(options: &[String]) -> String {
+    if options.is_empty() {
+        String::new()
+    } else {
+        format!("[{}]", options.join(", "))
+    }
+}
+
+fn verbatim_environment(begin: &str, code: &str, end: &str) -> String {
+    if code.is_empty() {
+        format!("{}\n{}", begin, end)
+    } else {
+        format!("{}\n{}\n{}", begin, code, end)
+    }
+}
+
+/// Puts `indent` before every line of `text` that is not empty.
+///
+/// ```
+/// use saurus::transpiler::generator;
+/// assert_eq!(generator::indent_lines("a\n\nb", "  "), "  a\n\n  b");
+/// ```
+pub fn indent_lines(text: &str, indent: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", indent, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Everything up to and including `\begin{document}`
+pub fn prologue(resources: &Resources, options: &Options) -> String {
+    let code_blocks = resources.contains(&Resource::CodeBlocks);
+    let code_backend = options.code_backend;
+    let listings = code_backend == CodeBackend::Listings;
+    let mut customizations = String::new();
+    if listings {
+        customizations.push_str(&style_definitions(
+            resources.listing_styles(),
+            &options.listing_styles,
+        ));
+        customizations.push_str(&language_definitions(resources.languages()));
+        if resources.contains(&Resource::HighlightedLines) {
+            customizations.push_str(highlight_customizations());
+        }
+    } else if code_blocks {
+        customizations.push_str(code_backend_customizations(code_backend));
+    }
+    format!(
+        "{}\n{}{}\n\\begin{{document}}\n{}\n",
+        documentclass(),
+        packages(),
+        customizations,
+        qol_customizations()
+    )
+}
+
+/// The definitions of the listing styles used.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use saurus::transpiler::generator;
+///
+/// let definitions = generator::style_definitions(["dark"].into_iter(), &HashMap::new());
+/// assert!(definitions.contains(r"\lstdefinestyle{dark}"));
+/// assert!(definitions.contains(r"\definecolor{dark-background}{HTML}{282C34}"));
+/// ```
+pub fn style_definitions<'a>(
+    names: impl Iterator<Item = &'a str>,
+    defined: &HashMap<String, ListingStyle>,
+) -> String {
+    names
+        .filter_map(|name| Some(styles::definition(name, &styles::find(name, defined)?)))
+        .collect()
+}
+
+/// The bundled definitions of the languages used, which listings lacks.
+///
+/// ```
+/// use saurus::transpiler::generator;
+///
+/// let definitions = generator::language_definitions(["rust", "python"].into_iter());
+/// assert!(definitions.contains(r"\lstdefinelanguage{rust}"));
+/// assert!(!definitions.contains("python"));
+/// ```
+pub fn language_definitions<'a>(languages: impl Iterator<Item = &'a str>) -> String {
+    languages.filter_map(code_blocks::definition).collect()
+}
+
+pub fn epilogue() -> String {
+    "\\end{document}\n".to_string()
 }
 
 const PACKAGES: [&str; 7] = [
@@ -134,39 +681,58 @@ const PACKAGES: [&str; 7] = [
     "enumitem",
 ];
 
-pub fn packages(contains_code_block: bool) -> String {
+pub fn packages() -> String {
     let mut packages = String::new();
     for package in PACKAGES.into_iter() {
         packages.push_str(&format!("\\usepackage{{{}}}\n", package));
     }
-    if contains_code_block {
-        packages.push_str(code_block_customizations());
-    }
     packages.push_str(hyperlink_customizations());
     packages
 }
 
 pub fn documentclass() -> String {
-    format!("\\documentclass{{article}}\n")
-}
-
-pub fn code_block_customizations() -> &'static str {
-    const CUSTOMS: &str = r"
-    \definecolor{codegreen}{rgb}{0, 0.6, 0}
-    \definecolor{backcolour}{rgb}{0.95,0.95,0.92}
-    \lstdefinestyle{myStyle}{
-        keywordstyle=\color{blue},
-        identifierstyle=\color{violet},
-        commentstyle=\color{codegreen},
-        backgroundcolor=\color{backcolour},
-        %basicstyle=\normal,
-        showspaces=false,
-        showstringspaces=false,
-        keepspaces=true,
-        extendedchars=true,
-        %numbers=left,
-    }";
-    CUSTOMS
+    "\\documentclass{article}\n".to_string()
+}
+
+/// The packages code blocks need when they are not written for listings
+pub fn code_backend_customizations(code_backend: CodeBackend) -> &'static str {
+    match code_backend {
+        // listings is set up by the styles code blocks use
+        CodeBackend::Listings => "",
+        CodeBackend::Minted => {
+            r"
+    % minted highlights code with Pygments, so compile with -shell-escape
+    \usepackage{minted}"
+        }
+        CodeBackend::Verbatim => "",
+        CodeBackend::Fancyvrb => {
+            r"
+    \usepackage{fancyvrb}"
+        }
+        // The colored `Verbatim`s read `\`, `{` and `}` as commands, so the
+        // code writes them with `\HL...` and sits on the theme's background
+        CodeBackend::Builtin => {
+            r"
+    \usepackage{fancyvrb}
+    \newcommand{\HLbackslash}{\char`\\}
+    \newcommand{\HLlbrace}{\char`\{}
+    \newcommand{\HLrbrace}{\char`\}}
+    % framed's shading breaks across pages along with the code
+    \usepackage{framed}
+    \newenvironment{highlighted}[1]{%
+        \definecolor{shadecolor}{HTML}{#1}\begin{snugshade}%
+    }{%
+        \end{snugshade}%
+    }"
+        }
+    }
+}
+
+pub fn highlight_customizations() -> &'static str {
+    const HIGHLIGHTING: &str = r"
+    \definecolor{highlight}{rgb}{1, 0.95, 0.6}
+    \newcommand{\highlightedline}{\makebox[0pt][l]{\color{highlight}\rule[-0.3em]{\linewidth}{1.1em}}}";
+    HIGHLIGHTING
 }
 
 pub fn hyperlink_customizations() -> &'static str {
@@ -0,0 +1,151 @@
+//! Highlighting of code blocks at transpile time, with grammars and themes
+//! bundled into saurus, for the `builtin` code backend.
+//!
+//! Highlighted lines are written for fancyvrb's `Verbatim` with
+//! `commandchars=\\\{\}`, so every color is a `\textcolor` command and
+//! backslashes and braces in the code are written as commands too.
+//!
+//! ```
+//! use saurus::transpiler::highlight;
+//!
+//! let highlighted = highlight::highlight("let x = 1;", Some("rust"), "InspiredGitHub").unwrap();
+//! assert_eq!(highlighted.lines.len(), 1);
+//! assert!(highlighted.lines[0].contains(r"\textcolor[HTML]"));
+//! assert!(highlight::highlight("{}", None, "InspiredGitHub").unwrap().lines[0].contains(r"\HLlbrace{}"));
+//! ```
+
+use std::sync::OnceLock;
+
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, FontStyle, Style, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// The theme code is colored with unless another is chosen
+pub const DEFAULT_THEME: &str = "InspiredGitHub";
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn themes() -> &'static ThemeSet {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+/// The names of the bundled themes
+pub fn theme_names() -> impl Iterator<Item = &'static str> {
+    themes().themes.keys().map(String::as_str)
+}
+
+pub fn is_theme(name: &str) -> bool {
+    themes().themes.contains_key(name)
+}
+
+/// Whether a grammar is bundled for `language`, a name such as `rust` or
+/// `Rust` or a file extension such as `rs`. Plain text has one too.
+///
+/// ```
+/// use saurus::transpiler::highlight;
+/// assert!(highlight::has_grammar("css"));
+/// assert!(highlight::has_grammar("text"));
+/// assert!(!highlight::has_grammar("zig"));
+/// ```
+pub fn has_grammar(language: &str) -> bool {
+    matches!(language, "text" | "plain" | "plaintext")
+        || syntaxes().find_syntax_by_token(language).is_some()
+}
+
+/// Code colored by a theme, ready for a `Verbatim` with `commandchars`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlighted {
+    pub lines: Vec<String>,
+    /// The theme's text color, which the lines leave uncolored, in hex
+    pub foreground: String,
+    /// The theme's background color, in hex
+    pub background: String,
+}
+
+/// Highlights `code` as `language` with the bundled theme `theme`, or as
+/// plain text if there is no grammar for it. `None` if there is no such
+/// theme.
+pub fn highlight(code: &str, language: Option<&str>, theme: &str) -> Option<Highlighted> {
+    let theme = themes().themes.get(theme)?;
+    let syntaxes = syntaxes();
+    let syntax = language
+        .and_then(|language| syntaxes.find_syntax_by_token(language))
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+    let foreground = theme.settings.foreground.unwrap_or(Color::BLACK);
+    let background = theme.settings.background.unwrap_or(Color::WHITE);
+
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let mut lines = Vec::new();
+    for line in LinesWithEndings::from(code) {
+        // A line that does not parse is better shown plain than not at all
+        let Ok(ranges) = highlighter.highlight_line(line, syntaxes) else {
+            lines.push(escape(line.trim_end_matches(['\n', '\r'])));
+            continue;
+        };
+        // Grammars split text more finely than themes color it
+        let mut pieces: Vec<(Style, String)> = Vec::new();
+        for (style, piece) in ranges {
+            let piece = piece.trim_end_matches(['\n', '\r']);
+            match pieces.last_mut() {
+                Some((last, text)) if *last == style => text.push_str(piece),
+                _ => pieces.push((style, piece.to_string())),
+            }
+        }
+        let mut text = String::new();
+        for (style, piece) in pieces {
+            let piece = escape(&piece);
+            if piece.trim().is_empty() {
+                text.push_str(&piece);
+                continue;
+            }
+            let mut piece = piece;
+            if style.font_style.contains(FontStyle::BOLD) {
+                piece = format!("\\textbf{{{}}}", piece);
+            }
+            if style.font_style.contains(FontStyle::ITALIC) {
+                piece = format!("\\textit{{{}}}", piece);
+            }
+            if style.foreground != foreground {
+                piece = format!(
+                    "\\textcolor[HTML]{{{}}}{{{}}}",
+                    hex(style.foreground),
+                    piece
+                );
+            }
+            text.push_str(&piece);
+        }
+        lines.push(text);
+    }
+    // A blank last line has no line ending of its own
+    if code.ends_with('\n') {
+        lines.push(String::new());
+    }
+    Some(Highlighted {
+        lines,
+        foreground: hex(foreground),
+        background: hex(background),
+    })
+}
+
+/// Writes the characters `commandchars` makes special as commands
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\HLbackslash{}"),
+            '{' => escaped.push_str("\\HLlbrace{}"),
+            '}' => escaped.push_str("\\HLrbrace{}"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn hex(color: Color) -> String {
+    format!("{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+}
@@ -0,0 +1,305 @@
+//! Standalone HTML output, styled to resemble the LaTeX rendering.
+
+use std::collections::HashMap;
+
+use crate::transpiler::ast::{self, CodeBlock, Heading, Inline, List, ListKind, Quote, RawLatex};
+use crate::transpiler::backend::{self, Backend, Context, Fragment};
+use crate::transpiler::generator::indent_lines;
+use crate::transpiler::lexer::Span;
+
+#[derive(Debug, Clone, Default)]
+pub struct Html {
+    /// Plain text of the first heading, used as the page title
+    title: Option<String>,
+    /// How many headings have used each id so far
+    ids: HashMap<String, usize>,
+}
+
+impl Html {
+    pub fn new() -> Self {
+        Html::default()
+    }
+
+    /// A unique id for a heading with the given text
+    fn heading_id(&mut self, text: &str) -> String {
+        let slug = slug(text);
+        let count = self.ids.entry(slug.clone()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            slug
+        } else {
+            format!("{}-{}", slug, *count - 1)
+        }
+    }
+}
+
+impl Backend for Html {
+    fn prologue(&mut self, _cx: &mut Context) -> String {
+        format!(
+            "<!DOCTYPE html>\n\
+             <html lang=\"en\">\n\
+             <head>\n\
+             <meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>{}</title>\n\
+             <style>\n{}</style>\n\
+             </head>\n\
+             <body>\n\
+             <main>\n\n",
+            escape(self.title.as_deref().unwrap_or("Document")),
+            STYLE
+        )
+    }
+
+    fn epilogue(&mut self, _cx: &mut Context) -> String {
+        "</main>\n</body>\n</html>\n".to_string()
+    }
+
+    fn comment(&mut self, text: &str) -> String {
+        format!("<!-- {} -->", text.replace("--", "- -"))
+    }
+
+    fn heading(&mut self, heading: &Heading, cx: &mut Context) -> Vec<Fragment> {
+        let plain = ast::plain_text(&heading.content);
+        if self.title.is_none() {
+            self.title = Some(plain.clone());
+        }
+        let id = self.heading_id(&plain);
+        let level = heading.level.clamp(1, 6);
+        let text = format!(
+            "<h{} id=\"{}\">{}</h{}>",
+            level,
+            id,
+            self.inlines(&heading.content, cx),
+            level
+        );
+        vec![Fragment::new(text, heading.span)]
+    }
+
+    fn paragraph(&mut self, paragraph: &ast::Paragraph, cx: &mut Context) -> Vec<Fragment> {
+        let text = format!("<p>{}</p>", self.inlines(&paragraph.content, cx));
+        vec![Fragment::new(text, paragraph.span)]
+    }
+
+    fn list(&mut self, list: &List, cx: &mut Context) -> Vec<Fragment> {
+        let indent = "    ".repeat(cx.depth);
+        let mut fragments = Vec::new();
+        for item in list.items.iter() {
+            let line = self.inlines(&item.content, cx);
+            if item.children.is_empty() {
+                fragments.push(Fragment::new(
+                    format!("{}  <li>{}</li>", indent, line),
+                    Span::line(item.span.start),
+                ));
+                continue;
+            }
+            fragments.push(Fragment::new(
+                format!("{}  <li>{}", indent, line),
+                Span::line(item.span.start),
+            ));
+            cx.depth += 1;
+            for child in item.children.iter() {
+                fragments.extend(self.block(child, cx).into_iter().map(|mut fragment| {
+                    if !matches!(child, ast::Block::List(_)) {
+                        fragment.text = indent_markup(&fragment.text, &"    ".repeat(cx.depth));
+                    }
+                    fragment
+                }));
+            }
+            cx.depth -= 1;
+            if let Some(last) = fragments.last_mut() {
+                last.text.push_str(&format!("\n{}  </li>", indent));
+            }
+        }
+
+        let (open, close) = match list.kind {
+            ListKind::Unordered => ("<ul>".to_string(), "</ul>"),
+            ListKind::Ordered { start: 1 } => ("<ol>".to_string(), "</ol>"),
+            ListKind::Ordered { start } => (format!("<ol start=\"{}\">", start), "</ol>"),
+        };
+        match fragments.first_mut() {
+            Some(first) => first.text.insert_str(0, &format!("{}{}\n", indent, open)),
+            None => fragments.push(Fragment::new(format!("{}{}", indent, open), list.span)),
+        }
+        if let Some(last) = fragments.last_mut() {
+            last.text.push_str(&format!("\n{}{}", indent, close));
+        }
+        fragments
+    }
+
+    fn quote(&mut self, quote: &Quote, cx: &mut Context) -> Vec<Fragment> {
+        let depth = cx.depth;
+        cx.depth = 0;
+        let mut body = Vec::new();
+        for block in quote.blocks.iter() {
+            let text = self
+                .block(block, cx)
+                .into_iter()
+                .map(|fragment| fragment.text)
+                .collect::<Vec<_>>()
+                .join("\n");
+            body.push(indent_markup(&text, "  "));
+        }
+        cx.depth = depth;
+        let text = format!("<blockquote>\n{}\n</blockquote>", body.join("\n"));
+        vec![Fragment::new(text, quote.span)]
+    }
+
+    fn code_block(&mut self, code_block: &CodeBlock, _cx: &mut Context) -> Vec<Fragment> {
+        let class = code_block
+            .language
+            .as_ref()
+            .map_or(String::new(), |language| {
+                format!(" class=\"language-{}\"", escape(language))
+            });
+        let attributes = &code_block.attributes;
+        let id = attributes
+            .label
+            .as_ref()
+            .map_or(String::new(), |label| format!(" id=\"{}\"", escape(label)));
+        let mut text = format!(
+            "<pre{}><code{}>{}</code></pre>",
+            id,
+            class,
+            escape(&code_block.code)
+        );
+        if let Some(caption) = &attributes.caption {
+            text = format!(
+                "<figure>\n<figcaption>{}</figcaption>\n{}\n</figure>",
+                escape(caption),
+                text
+            );
+        }
+        vec![Fragment::new(text, code_block.span)]
+    }
+
+    fn text(&mut self, text: &str, _cx: &mut Context) -> String {
+        escape(text)
+    }
+
+    fn bold(&mut self, content: &[Inline], cx: &mut Context) -> String {
+        format!("<strong>{}</strong>", self.inlines(content, cx))
+    }
+
+    fn italic(&mut self, content: &[Inline], cx: &mut Context) -> String {
+        format!("<em>{}</em>", self.inlines(content, cx))
+    }
+
+    fn strike(&mut self, content: &[Inline], cx: &mut Context) -> String {
+        format!("<del>{}</del>", self.inlines(content, cx))
+    }
+
+    fn code(&mut self, code: &str, _cx: &mut Context) -> String {
+        format!("<code>{}</code>", escape(code))
+    }
+
+    fn link(&mut self, content: &[Inline], url: &str, cx: &mut Context) -> String {
+        format!(
+            "<a href=\"{}\">{}</a>",
+            escape(url),
+            self.inlines(content, cx)
+        )
+    }
+
+    fn math(&mut self, math: &str, _cx: &mut Context) -> String {
+        format!("<span class=\"math\">{}</span>", escape(math))
+    }
+
+    fn raw_block(&mut self, raw: &RawLatex, cx: &mut Context) -> Vec<Fragment> {
+        backend::drop_raw_latex(raw.span.start, cx);
+        Vec::new()
+    }
+
+    fn raw_inline(&mut self, _latex: &str, cx: &mut Context) -> String {
+        backend::drop_raw_latex(0, cx);
+        String::new()
+    }
+
+    fn line_break(&mut self, _cx: &mut Context) -> String {
+        "<br>\n".to_string()
+    }
+}
+
+const STYLE: &str = r#"body {
+    margin: 0;
+    background: #fdfdfd;
+    color: #111;
+}
+main {
+    max-width: 42em;
+    margin: 2em auto;
+    padding: 0 1em;
+    font: 12pt/1.5 "Latin Modern Roman", "Computer Modern Serif", Georgia, serif;
+}
+p + p {
+    text-indent: 1.5em;
+    margin-top: -0.5em;
+}
+a {
+    color: blue;
+}
+blockquote {
+    margin: 1em 2.5em;
+}
+pre {
+    background: rgb(242, 242, 235);
+    padding: 0.5em;
+    overflow-x: auto;
+}
+code {
+    font-family: "Latin Modern Mono", "Courier New", monospace;
+}
+.math {
+    font-style: italic;
+}
+"#;
+
+/// Escapes text for use in HTML content and attribute values.
+///
+/// ```
+/// use saurus::transpiler::html;
+/// assert_eq!(html::escape(r#"<a href="x">&</a>"#), "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+/// ```
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// An id for a heading: lowercase alphanumerics joined by dashes.
+///
+/// ```
+/// use saurus::transpiler::html;
+/// assert_eq!(html::slug("The Reconstruction Era!"), "the-reconstruction-era");
+/// ```
+pub fn slug(text: &str) -> String {
+    let slug = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Indents `text` unless it holds a `<pre>`, whose lines would take the
+/// indentation into the code.
+fn indent_markup(text: &str, indent: &str) -> String {
+    if text.contains("<pre>") {
+        text.to_string()
+    } else {
+        indent_lines(text, indent)
+    }
+}
@@ -0,0 +1,176 @@
+//! Code blocks that list a file, such as ```` ```rust file=src/main.rs lines=10-40 ````.
+//!
+//! LaTeX documents refer to the file, e.g. with `\lstinputlisting`, so it is
+//! read when the document is compiled, from [`Options::output_dir`]. Everything
+//! else, and LaTeX with [`Options::inline_files`], gets the file's lines
+//! written into the block.
+//!
+//! Paths are not confined to [`Options::base_dir`]: absolute paths and `..`
+//! reach any file saurus can read, so only transpile Markdown you trust, as
+//! you would only compile LaTeX you trust.
+//!
+//! ```
+//! use saurus::transpiler::{include, parse, Options, Target};
+//! use saurus::transpiler::ast::Block;
+//!
+//! let base_dir = std::env::temp_dir();
+//! std::fs::write(base_dir.join("saurus-include.py"), "a = 1\nb = 2\nc = 3\n").unwrap();
+//!
+//! let source = "```python file=saurus-include.py lines=2-3\n```";
+//! let options = Options { target: Target::Html, base_dir, ..Options::default() };
+//! let mut diagnostics = Vec::new();
+//! let mut document = parse(source, &mut diagnostics);
+//! include::resolve(&mut document, &options, &mut diagnostics);
+//! match &document.blocks[0] {
+//!     Block::CodeBlock(code_block) => assert_eq!(code_block.code, "b = 2\nc = 3"),
+//!     block => panic!("expected a code block, found {:?}", block),
+//! }
+//! assert!(diagnostics.is_empty());
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use crate::transpiler::ast::{CodeBlock, Document};
+use crate::transpiler::code_blocks::CodeBackend;
+use crate::transpiler::diagnostics::Diagnostic;
+use crate::transpiler::visit::{walk_document_mut, VisitorMut};
+use crate::transpiler::{Options, Target};
+
+/// Checks that every file code blocks list exists and has the lines they
+/// list, writing the lines into the blocks that cannot refer to the file.
+/// The others refer to it from the output directory.
+///
+/// ```
+/// use saurus::transpiler::{include, parse, Options};
+/// use saurus::transpiler::ast::Block;
+///
+/// let base_dir = std::env::temp_dir();
+/// std::fs::write(base_dir.join("saurus-output.py"), "a = 1\n").unwrap();
+/// let output_dir = base_dir.join("saurus-build");
+/// std::fs::create_dir_all(&output_dir).unwrap();
+///
+/// let source = "```python file=saurus-output.py\n```";
+/// let options = Options { base_dir, output_dir: Some(output_dir), ..Options::default() };
+/// let mut document = parse(source, &mut Vec::new());
+/// include::resolve(&mut document, &options, &mut Vec::new());
+/// match &document.blocks[0] {
+///     Block::CodeBlock(code_block) => {
+///         assert_eq!(code_block.attributes.file.as_deref(), Some("../saurus-output.py"))
+///     }
+///     block => panic!("expected a code block, found {:?}", block),
+/// }
+/// ```
+pub fn resolve(document: &mut Document, options: &Options, diagnostics: &mut Vec<Diagnostic>) {
+    let mut resolver = Resolver {
+        options,
+        diagnostics,
+    };
+    walk_document_mut(&mut resolver, document);
+}
+
+struct Resolver<'a> {
+    options: &'a Options,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl VisitorMut for Resolver<'_> {
+    fn visit_code_block_mut(&mut self, code_block: &mut CodeBlock) {
+        let attributes = &mut code_block.attributes;
+        let Some(file) = attributes.file.clone() else {
+            return;
+        };
+        let line = code_block.span.start;
+        if !code_block.code.is_empty() {
+            self.diagnostics.push(
+                Diagnostic::warning(line, 1, "code block lists a file and code")
+                    .with_hint(format!("the code is replaced by `{}`", file)),
+            );
+        }
+
+        let path = self.options.base_dir.join(&file);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                self.diagnostics.push(
+                    Diagnostic::error(line, 1, format!("unable to include `{}`", file))
+                        .with_hint(format!("reading {} failed: {}", path.display(), error)),
+                );
+                return;
+            }
+        };
+        let count = contents.lines().count();
+        let (start, end) = attributes.lines.unwrap_or((1, count));
+        if end > count {
+            self.diagnostics.push(
+                Diagnostic::error(line, 1, format!("`{}` has no line {}", file, end))
+                    .with_hint(format!("it has {} lines", count)),
+            );
+            return;
+        }
+
+        // listings writes highlights into the lines, `verbatim` cannot read
+        // files and saurus colors the lines itself, so only inlined code
+        // works for them
+        let inline = match self.options.code_backend {
+            _ if self.options.inline_files || self.options.target != Target::Latex => true,
+            CodeBackend::Listings => !attributes.highlight.is_empty(),
+            CodeBackend::Verbatim | CodeBackend::Builtin => true,
+            CodeBackend::Minted | CodeBackend::Fancyvrb => false,
+        };
+        if !inline {
+            if let Some(output_dir) = &self.options.output_dir {
+                attributes.file = Some(relative_path(&path, output_dir));
+            }
+            return;
+        }
+        code_block.code = contents
+            .lines()
+            .skip(start - 1)
+            .take(end + 1 - start)
+            .collect::<Vec<_>>()
+            .join("\n");
+        // Lines keep their numbers in the file, like `\lstinputlisting` numbers them
+        if attributes.line_numbers && attributes.first_line.is_none() && start > 1 {
+            attributes.first_line = Some(start);
+        } else if attributes.first_line.is_none() {
+            attributes.highlight = attributes
+                .highlight
+                .iter()
+                .filter(|(_, last)| *last >= start)
+                .map(|(first, last)| (first.max(&start) + 1 - start, last + 1 - start))
+                .collect();
+        }
+        attributes.file = None;
+        attributes.lines = None;
+    }
+}
+
+/// `path` as LaTeX finds it from `dir`, or absolute if they have no common
+/// ancestor
+fn relative_path(path: &Path, dir: &Path) -> String {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let Ok(dir) = fs::canonicalize(dir) else {
+        return tex_path(&path);
+    };
+    let common = path
+        .components()
+        .zip(dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return tex_path(&path);
+    }
+    let mut relative: Vec<String> = vec!["..".to_string(); dir.components().count() - common];
+    relative.extend(
+        path.components()
+            .skip(common)
+            .map(|component| component.as_os_str().to_string_lossy().into_owned()),
+    );
+    relative.join("/")
+}
+
+/// TeX paths separate directories with `/` everywhere
+fn tex_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
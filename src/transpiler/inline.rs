@@ -0,0 +1,170 @@
+use crate::transpiler::ast::Inline;
+
+/// Characters that lose their Markdown meaning when preceded by a backslash.
+/// Backslashes before anything else are kept, so raw LaTeX like `\LaTeX` or
+/// `\\` passes through untouched.
+const ESCAPABLE: &str = r"*~`$&[]_#%>-+.!()";
+
+/// Parses the inline formatting of a single line of text.
+///
+/// ```
+/// use saurus::transpiler::{ast::Inline, inline};
+///
+/// assert_eq!(
+///     inline::parse("**bold** and *italic*"),
+///     vec![
+///         Inline::Bold(vec![Inline::Text("bold".to_string())]),
+///         Inline::Text(" and ".to_string()),
+///         Inline::Italic(vec![Inline::Text("italic".to_string())]),
+///     ]
+/// );
+/// assert_eq!(
+///     inline::parse("$5 costs $x^2$"),
+///     vec![
+///         Inline::Text("$5 costs ".to_string()),
+///         Inline::Math("x^2".to_string()),
+///     ]
+/// );
+/// assert_eq!(inline::parse("a => b"), vec![Inline::Text("a → b".to_string())]);
+/// assert_eq!(inline::parse(r"\*not italic\*"), vec![Inline::Text("*not italic*".to_string())]);
+/// ```
+pub fn parse(text: &str) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        if let Some((inline, after)) = parse_span(rest) {
+            if !plain.is_empty() {
+                inlines.push(Inline::Text(std::mem::take(&mut plain)));
+            }
+            inlines.push(inline);
+            rest = after;
+        } else if let Some((literal, after)) = parse_literal(rest) {
+            plain.push_str(literal);
+            rest = after;
+        } else {
+            plain.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if !plain.is_empty() {
+        inlines.push(Inline::Text(plain));
+    }
+    inlines
+}
+
+/// Parses the lines of a paragraph, joining them with soft breaks.
+pub fn parse_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    for (index, line) in lines.into_iter().enumerate() {
+        if index > 0 {
+            inlines.push(Inline::SoftBreak);
+        }
+        inlines.extend(parse(line.trim()));
+    }
+    inlines
+}
+
+/// Text that stands for something else: escapes and arrows.
+fn parse_literal(rest: &str) -> Option<(&str, &str)> {
+    let mut chars = rest.chars();
+    if chars.next() == Some('\\') {
+        let escaped = chars.next()?;
+        if ESCAPABLE.contains(escaped) {
+            return Some((&rest[1..2], &rest[2..]));
+        }
+        // Keep `\\` together so the second backslash cannot escape anything
+        if escaped == '\\' {
+            return Some((&rest[..2], &rest[2..]));
+        }
+    }
+    for arrow in ["=>", "&rarr;"] {
+        if let Some(after) = rest.strip_prefix(arrow) {
+            return Some(("→", after));
+        }
+    }
+    None
+}
+
+/// Formatting that wraps other text, returning it and the text after it.
+fn parse_span(rest: &str) -> Option<(Inline, &str)> {
+    if let Some(after) = rest.strip_prefix('`') {
+        let end = after.find('`').filter(|end| *end > 0)?;
+        return Some((Inline::Code(after[..end].to_string()), &after[end + 1..]));
+    }
+    if let Some(after) = rest.strip_prefix('$') {
+        // A dollar sign followed by a digit is currency, not math
+        if after.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        let end = after.find('$').filter(|end| *end > 0)?;
+        return Some((Inline::Math(after[..end].to_string()), &after[end + 1..]));
+    }
+    if let Some(after) = rest.strip_prefix("~~") {
+        let end = after.find("~~").filter(|end| *end > 0)?;
+        return Some((Inline::Strike(parse(&after[..end])), &after[end + 2..]));
+    }
+    if rest.starts_with('*') {
+        return parse_emphasis(rest);
+    }
+    if rest.starts_with('[') {
+        return parse_link(rest);
+    }
+    None
+}
+
+/// `*italic*`, `**bold**` and `***both***`, closed by a run of as many stars.
+fn parse_emphasis(rest: &str) -> Option<(Inline, &str)> {
+    let run = rest.chars().take_while(|c| *c == '*').count();
+    if run > 3 {
+        return None;
+    }
+    let after = &rest[run..];
+    if after.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let mut index = 0;
+    while let Some(offset) = after[index..].find('*') {
+        let start = index + offset;
+        let length = after[start..].chars().take_while(|c| *c == '*').count();
+        if length == run && start > 0 {
+            let content = parse(&after[..start]);
+            let inline = match run {
+                1 => Inline::Italic(content),
+                2 => Inline::Bold(content),
+                _ => Inline::Bold(vec![Inline::Italic(content)]),
+            };
+            return Some((inline, &after[start + length..]));
+        }
+        index = start + length;
+    }
+    None
+}
+
+/// `[text](url)`
+fn parse_link(rest: &str) -> Option<(Inline, &str)> {
+    let mut depth = 0;
+    let close = rest.char_indices().find_map(|(index, c)| {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        (depth == 0).then_some(index)
+    })?;
+    let after = rest[close + 1..].strip_prefix('(')?;
+    let end = after.find(')').filter(|end| *end > 0)?;
+    let url = &after[..end];
+    if url.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((
+        Inline::Link {
+            content: parse(&rest[1..close]),
+            url: url.to_string(),
+        },
+        &after[end + 1..],
+    ))
+}
@@ -0,0 +1,540 @@
+//! Reads LaTeX back into a document tree.
+//!
+//! Only the subset saurus itself generates is understood: sectioning
+//! commands, `itemize` and `enumerate` (with `\setcounter`), `quote`,
+//! `lstlisting` and the inline commands `\textbf`, `\textit`, `\sout`, `\verb`
+//! and `\href`. Anything else is kept as raw LaTeX text, which saurus passes
+//! through untouched.
+//!
+//! ```
+//! use saurus::transpiler::{ast::Block, latex};
+//!
+//! let document = latex::parse("\\section{Notes}\n\nSome \\textbf{bold} text", &mut Vec::new());
+//! assert!(matches!(document.blocks[0], Block::Heading(_)));
+//! assert!(matches!(document.blocks[1], Block::Paragraph(_)));
+//! ```
+
+use crate::transpiler::ast::{
+    self, Block, CodeAttributes, CodeBlock, Document, Heading, Inline, Item, List, ListKind,
+    Paragraph, Quote,
+};
+use crate::transpiler::diagnostics::Diagnostic;
+use crate::transpiler::generator;
+use crate::transpiler::lexer::Span;
+use crate::transpiler::styles;
+
+/// Sectioning commands and the heading level they stand for
+const SECTIONS: [(&str, usize); 3] = [("section", 1), ("subsection", 2), ("subsubsection", 3)];
+
+/// Parses the body of a LaTeX document. Without a `\begin{document}` the
+/// whole input is read as the body. Spans refer to lines of the LaTeX.
+///
+/// ```
+/// use saurus::transpiler::{ast::Block, latex};
+///
+/// // Listings in the default style, under its name in older output too
+/// let tex = "\\begin{lstlisting}[language=python, style=myStyle]\nx = 1\n\\end{lstlisting}";
+/// match &latex::parse(tex, &mut Vec::new()).blocks[0] {
+///     Block::CodeBlock(code_block) => assert_eq!(code_block.attributes.style, None),
+///     block => panic!("expected a code block, found {:?}", block),
+/// }
+/// ```
+pub fn parse(input: &str, diagnostics: &mut Vec<Diagnostic>) -> Document {
+    let lines: Vec<&str> = input.lines().collect();
+    let start = lines
+        .iter()
+        .position(|line| strip_comment(line).trim() == "\\begin{document}")
+        .map_or(0, |index| index + 1);
+    let mut reader = Reader {
+        lines,
+        position: start,
+        diagnostics,
+    };
+    Document {
+        blocks: reader.blocks(Until::Document),
+    }
+}
+
+/// What ends the blocks being read
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Until<'a> {
+    Document,
+    Environment(&'a str),
+    /// The next `\item` of a list, or the end of the list's environment
+    Item(&'a str),
+}
+
+struct Reader<'a, 'b> {
+    lines: Vec<&'a str>,
+    /// Index of the next line to read
+    position: usize,
+    diagnostics: &'b mut Vec<Diagnostic>,
+}
+
+impl<'a, 'b> Reader<'a, 'b> {
+    fn blocks(&mut self, until: Until) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        let mut paragraph: Vec<(usize, &str)> = Vec::new();
+
+        while let Some(raw) = self.lines.get(self.position).copied() {
+            let number = self.position + 1;
+            let line = strip_comment(raw).trim();
+            if line.is_empty() {
+                self.position += 1;
+                // Comment lines do not end a paragraph
+                if raw.trim().is_empty() {
+                    flush_paragraph(&mut paragraph, &mut blocks);
+                }
+                continue;
+            }
+
+            if let Some(environment) = environment_name(line, "\\end") {
+                match until {
+                    Until::Environment(name) | Until::Item(name) if name == environment => {
+                        if let Until::Environment(_) = until {
+                            self.position += 1;
+                        }
+                        break;
+                    }
+                    Until::Document if environment == "document" => {
+                        self.position = self.lines.len();
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            if matches!(until, Until::Item(_)) && line.starts_with("\\item") {
+                break;
+            }
+
+            if let Some((level, content)) = section(line) {
+                flush_paragraph(&mut paragraph, &mut blocks);
+                self.position += 1;
+                blocks.push(Block::Heading(Heading {
+                    level,
+                    content: parse_inlines(content),
+                    span: Span::line(number),
+                }));
+            } else if let Some(environment) = environment_name(line, "\\begin") {
+                flush_paragraph(&mut paragraph, &mut blocks);
+                match environment {
+                    "itemize" | "enumerate" => blocks.push(Block::List(self.list(environment))),
+                    "quote" | "quotation" => blocks.push(Block::Quote(self.quote(environment))),
+                    "lstlisting" | "verbatim" => {
+                        blocks.push(Block::CodeBlock(self.code_block(environment)))
+                    }
+                    _ => {
+                        self.diagnostics.push(
+                            Diagnostic::warning(
+                                number,
+                                raw.len() - raw.trim_start().len() + 1,
+                                format!("unsupported environment `{}`", environment),
+                            )
+                            .with_hint("its lines are kept as text"),
+                        );
+                        paragraph.push((number, line));
+                        self.position += 1;
+                    }
+                }
+            } else if let Some(code_block) = input_listing(line, number) {
+                flush_paragraph(&mut paragraph, &mut blocks);
+                self.position += 1;
+                blocks.push(Block::CodeBlock(code_block));
+            } else if line.starts_with("\\setcounter{secnumdepth}") {
+                self.position += 1;
+            } else {
+                paragraph.push((number, line));
+                self.position += 1;
+            }
+        }
+        flush_paragraph(&mut paragraph, &mut blocks);
+        blocks
+    }
+
+    fn list(&mut self, environment: &'a str) -> List {
+        let begin = self.position + 1;
+        self.position += 1;
+        let kind = match environment {
+            "enumerate" => ListKind::Ordered { start: 1 },
+            _ => ListKind::Unordered,
+        };
+        let mut list = List {
+            kind,
+            items: Vec::new(),
+            span: Span::line(begin),
+        };
+
+        while let Some(raw) = self.lines.get(self.position).copied() {
+            let number = self.position + 1;
+            let line = strip_comment(raw).trim();
+            if environment_name(line, "\\end") == Some(environment) {
+                self.position += 1;
+                list.span.end = number;
+                break;
+            }
+            if let Some(rest) = line.strip_prefix("\\setcounter{enum") {
+                if let (ListKind::Ordered { start }, Some(value)) = (&mut list.kind, counter(rest))
+                {
+                    *start = value + 1;
+                }
+                self.position += 1;
+            } else if let Some(rest) = line.strip_prefix("\\item") {
+                self.position += 1;
+                let mut content = parse_inlines(rest.trim());
+                let mut children = self.blocks(Until::Item(environment));
+                // Lines wrapped right after `\item` continue its text
+                if let Some(Block::Paragraph(paragraph)) = children.first() {
+                    if paragraph.span.start == number + 1 {
+                        if let Block::Paragraph(paragraph) = children.remove(0) {
+                            content.push(Inline::SoftBreak);
+                            content.extend(paragraph.content);
+                        }
+                    }
+                }
+                let end = children.last().map_or(number, |child| child.span().end);
+                list.items.push(Item {
+                    content,
+                    children,
+                    span: Span::new(number, end),
+                });
+            } else {
+                self.position += 1;
+            }
+        }
+        list
+    }
+
+    fn quote(&mut self, environment: &'a str) -> Quote {
+        let begin = self.position + 1;
+        self.position += 1;
+        let blocks = self.blocks(Until::Environment(environment));
+        Quote {
+            blocks,
+            span: Span::new(begin, self.position),
+        }
+    }
+
+    fn code_block(&mut self, environment: &'a str) -> CodeBlock {
+        let begin = self.position + 1;
+        let line = self.lines[self.position];
+        let indent = line.len() - line.trim_start().len();
+        let options = line
+            .split_once('[')
+            .map_or("", |(_, options)| options.trim_end().trim_end_matches(']'));
+        let (language, mut attributes) = listing_options(options);
+        self.position += 1;
+
+        let end = format!("\\end{{{}}}", environment);
+        let mut code = Vec::new();
+        while let Some(line) = self.lines.get(self.position).copied() {
+            self.position += 1;
+            if line.trim() == end {
+                break;
+            }
+            let leading = line.len() - line.trim_start().len();
+            let line = &line[leading.min(indent)..];
+            match line.strip_prefix(generator::HIGHLIGHT) {
+                Some(line) => {
+                    let number = attributes.first_line() + code.len();
+                    match attributes.highlight.last_mut() {
+                        Some((_, end)) if *end + 1 == number => *end = number,
+                        _ => attributes.highlight.push((number, number)),
+                    }
+                    code.push(line);
+                }
+                None => code.push(line),
+            }
+        }
+        // saurus numbers the lines of every listing with a first line number
+        if attributes.first_line.is_some() {
+            attributes.line_numbers = false;
+        }
+        CodeBlock {
+            language,
+            attributes,
+            code: code.join("\n"),
+            span: Span::new(begin, self.position),
+        }
+    }
+}
+
+/// A `\lstinputlisting[options]{file}` line as a code block listing the file.
+fn input_listing(line: &str, number: usize) -> Option<CodeBlock> {
+    let rest = line.strip_prefix("\\lstinputlisting")?;
+    let (options, rest) = match rest.strip_prefix('[') {
+        Some(rest) => rest.split_once(']')?,
+        None => ("", rest),
+    };
+    let file = rest.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let (language, mut attributes) = listing_options(options);
+    attributes.file = Some(file.to_string());
+    Some(CodeBlock {
+        language,
+        attributes,
+        code: String::new(),
+        span: Span::line(number),
+    })
+}
+
+/// The language and attributes set by the options of a listing.
+fn listing_options(options: &str) -> (Option<String>, CodeAttributes) {
+    let mut language = None;
+    let mut attributes = CodeAttributes::default();
+    let (mut first, mut last) = (None, None);
+    for option in split_options(options) {
+        let (key, value) = option.split_once('=').unwrap_or((option, ""));
+        let value = value.trim();
+        let value = value
+            .strip_prefix('{')
+            .and_then(|value| value.strip_suffix('}'))
+            .unwrap_or(value);
+        match key.trim() {
+            "language" => language = Some(value.to_string()),
+            "caption" => {
+                attributes.caption = Some(ast::plain_text(&parse_inlines(value)));
+            }
+            "label" => attributes.label = Some(value.to_string()),
+            "numbers" => attributes.line_numbers = value != "none",
+            "firstnumber" => attributes.first_line = value.parse().ok(),
+            // Output from before listing styles were themeable always has `myStyle`
+            "style" if value != styles::DEFAULT_STYLE && value != "myStyle" => {
+                attributes.style = Some(value.to_string())
+            }
+            "firstline" => first = value.parse().ok(),
+            "lastline" => last = value.parse().ok(),
+            _ => {}
+        }
+    }
+    if let (Some(first), Some(last)) = (first, last) {
+        attributes.lines = Some((first, last));
+    }
+    (language, attributes)
+}
+
+/// Splits `key=value` options at the commas outside braces.
+fn split_options(options: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (index, c) in options.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                split.push(&options[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    split.push(&options[start..]);
+    split
+        .into_iter()
+        .filter(|option| !option.trim().is_empty())
+        .collect()
+}
+
+fn flush_paragraph(lines: &mut Vec<(usize, &str)>, blocks: &mut Vec<Block>) {
+    let (Some((start, _)), Some((end, _))) = (lines.first(), lines.last()) else {
+        return;
+    };
+    let span = Span::new(*start, *end);
+    let mut content = Vec::new();
+    for (index, (_, line)) in lines.iter().enumerate() {
+        let (line, hard) = match line.strip_suffix("\\\\") {
+            Some(line) => (line.trim_end(), true),
+            None => (*line, false),
+        };
+        content.extend(parse_inlines(line));
+        if index + 1 < lines.len() {
+            content.push(if hard {
+                Inline::LineBreak
+            } else {
+                Inline::SoftBreak
+            });
+        }
+    }
+    lines.clear();
+    blocks.push(Block::Paragraph(Paragraph { content, span }));
+}
+
+/// The text of a line up to an unescaped `%`
+fn strip_comment(line: &str) -> &str {
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '%' if !escaped => return &line[..index],
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    line
+}
+
+/// `name` of a line starting with `\begin{name}` or `\end{name}`
+fn environment_name<'a>(line: &'a str, command: &str) -> Option<&'a str> {
+    let (name, _) = line
+        .strip_prefix(command)?
+        .strip_prefix('{')?
+        .split_once('}')?;
+    Some(name)
+}
+
+/// The level and title of a sectioning command
+fn section(line: &str) -> Option<(usize, &str)> {
+    let command = line.strip_prefix('\\')?;
+    SECTIONS.iter().find_map(|(name, level)| {
+        let rest = command.strip_prefix(name)?;
+        let rest = rest.strip_prefix('*').unwrap_or(rest);
+        let (group, _) = group(rest)?;
+        Some((*level, group))
+    })
+}
+
+/// The value set by `\setcounter{enum...}{value}`, from just after `enum`
+fn counter(rest: &str) -> Option<usize> {
+    let (_, rest) = rest.split_once('}')?;
+    let (value, _) = group(rest)?;
+    value.trim().parse().ok()
+}
+
+/// Splits a brace-delimited group off the start of `text`, returning its
+/// contents and what follows it.
+fn group(text: &str) -> Option<(&str, &str)> {
+    let text = text.strip_prefix('{')?;
+    let mut depth = 0;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        match c {
+            '\\' => {
+                escaped = !escaped;
+                continue;
+            }
+            '{' if !escaped => depth += 1,
+            '}' if !escaped => {
+                if depth == 0 {
+                    return Some((&text[..index], &text[index + 1..]));
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+        escaped = false;
+    }
+    None
+}
+
+/// Parses the inline commands of a line of LaTeX.
+///
+/// ```
+/// use saurus::transpiler::{ast::Inline, latex};
+///
+/// assert_eq!(
+///     latex::parse_inlines(r"\textbf{50\%} of \verb|x| $\rightarrow$ \href{https://a.b/\#c}{here}"),
+///     vec![
+///         Inline::Bold(vec![Inline::Text("50%".to_string())]),
+///         Inline::Text(" of ".to_string()),
+///         Inline::Code("x".to_string()),
+///         Inline::Text(" → ".to_string()),
+///         Inline::Link {
+///             content: vec![Inline::Text("here".to_string())],
+///             url: "https://a.b/#c".to_string(),
+///         },
+///     ]
+/// );
+/// ```
+pub fn parse_inlines(text: &str) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        if let Some((inline, after)) = parse_command(rest) {
+            match inline {
+                Inline::Text(text) => plain.push_str(&text),
+                inline => {
+                    if !plain.is_empty() {
+                        inlines.push(Inline::Text(std::mem::take(&mut plain)));
+                    }
+                    inlines.push(inline);
+                }
+            }
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('\\') {
+            // Keep unknown commands, and the second backslash of `\\`, as written
+            let length = after
+                .chars()
+                .take_while(|c| c.is_ascii_alphabetic())
+                .map(char::len_utf8)
+                .sum::<usize>()
+                .max(after.chars().next().map_or(0, char::len_utf8));
+            plain.push_str(&rest[..1 + length]);
+            rest = &after[length..];
+        } else {
+            plain.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if !plain.is_empty() {
+        inlines.push(Inline::Text(plain));
+    }
+    inlines
+}
+
+/// An inline command at the start of `rest`, and the text after it.
+fn parse_command(rest: &str) -> Option<(Inline, &str)> {
+    if let Some(after) = rest.strip_prefix("$\\rightarrow$") {
+        return Some((Inline::Text("→".to_string()), after));
+    }
+    if let Some(after) = rest.strip_prefix('$') {
+        let end = after.find('$').filter(|end| *end > 0)?;
+        return Some((Inline::Math(after[..end].to_string()), &after[end + 1..]));
+    }
+
+    let after = rest.strip_prefix('\\')?;
+    let escaped = after.chars().next()?;
+    if "&$%#_".contains(escaped) {
+        return Some((Inline::Text(escaped.to_string()), &after[1..]));
+    }
+
+    let name_length = after
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .count();
+    let (name, after) = after.split_at(name_length);
+    match name {
+        "textbf" => {
+            let (content, after) = group(after)?;
+            Some((Inline::Bold(parse_inlines(content)), after))
+        }
+        "textit" | "emph" => {
+            let (content, after) = group(after)?;
+            Some((Inline::Italic(parse_inlines(content)), after))
+        }
+        "sout" => {
+            let (content, after) = group(after)?;
+            Some((Inline::Strike(parse_inlines(content)), after))
+        }
+        "verb" => {
+            let delimiter = after.chars().next()?;
+            let after = &after[delimiter.len_utf8()..];
+            let end = after.find(delimiter)?;
+            Some((
+                Inline::Code(after[..end].to_string()),
+                &after[end + delimiter.len_utf8()..],
+            ))
+        }
+        "href" => {
+            let (url, after) = group(after)?;
+            let (content, after) = group(after)?;
+            let url = url.replace("\\%", "%").replace("\\#", "#");
+            Some((
+                Inline::Link {
+                    content: parse_inlines(content),
+                    url,
+                },
+                after,
+            ))
+        }
+        _ => None,
+    }
+}
@@ -1,4 +1,8 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use std::fmt;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum Token {
     FileStart,
     FileEnd,
@@ -11,6 +15,12 @@ pub enum Token {
 
     CodeBlock,
 
+    /// A line between code fences, kept verbatim
+    Code,
+
+    /// A line of a code block indented by four spaces, without them
+    IndentedCode,
+
     BlockQuote,
 
     Text,
@@ -25,26 +35,88 @@ pub struct Lexer {
     pub results: Vec<Info>,
     pub number_of_lines: usize,
     pub contains_code_block: bool,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The character, length and indentation of the open code fence, if any
+    fence: Option<(char, usize, usize)>,
 }
 
-#[derive(Debug, Clone)]
+/// Inclusive range of 1-based source lines. Line 0 marks tokens that are
+/// not tied to a line, such as the start of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    pub fn line(line: usize) -> Self {
+        Span::new(line, line)
+    }
+
+    pub fn contains(&self, line: usize) -> bool {
+        self.start <= line && line <= self.end
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Info {
     pub token: Token,
     pub line: Option<String>,
     pub indent_level: usize,
+    pub span: Span,
 }
 
 impl Info {
-    fn new(token: Token, line: Option<String>, indent_level: usize) -> Self {
+    fn new(token: Token, line: Option<String>, indent_level: usize, line_number: usize) -> Self {
         Self {
             token,
             line,
             indent_level,
+            span: Span::line(line_number),
         }
     }
 }
 
+/// One token per line: its line, indent level, kind and contents.
+impl fmt::Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>4}  {}  {:?}",
+            self.span.to_string(),
+            self.indent_level,
+            self.token
+        )?;
+        if let Some(line) = &self.line {
+            write!(f, "  {:?}", line)?;
+        }
+        Ok(())
+    }
+}
+
+use crate::transpiler::diagnostics::Diagnostic;
 use crate::transpiler::re;
+
+impl Default for Lexer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Lexer {
     pub fn new() -> Self {
         let tokens: Vec<Info> = Vec::new();
@@ -54,49 +126,211 @@ impl Lexer {
             results: tokens,
             number_of_lines,
             contains_code_block,
+            diagnostics: Vec::new(),
+            fence: None,
         }
     }
+
+    /// Makes a token of every line of `file_str`. Lines between code fences
+    /// are kept verbatim, whatever Markdown they look like.
+    ///
+    /// ```
+    /// use saurus::transpiler::lexer::{Lexer, Token};
+    ///
+    /// let mut lexer = Lexer::new();
+    /// lexer.tokenize("# Title\n```bash\n# comment\n- x\n```");
+    /// let tokens: Vec<_> = lexer.results.iter().map(|info| info.token).collect();
+    /// assert_eq!(
+    ///     tokens[1..6],
+    ///     [Token::Heading(1), Token::CodeBlock, Token::Code, Token::Code, Token::CodeBlock]
+    /// );
+    /// assert_eq!(lexer.results[4].line.as_deref(), Some("- x"));
+    ///
+    /// // Inline code may start a line of text
+    /// let mut lexer = Lexer::new();
+    /// lexer.tokenize("`cargo build` builds it");
+    /// assert_eq!(lexer.results[1].token, Token::Text);
+    /// assert!(lexer.diagnostics.is_empty());
+    /// ```
     pub fn tokenize(&mut self, file_str: &str) {
-        self.results.push(Info::new(Token::FileStart, None, 0));
-        for (_line_number, line) in file_str.lines().enumerate() {
+        self.results.push(Info::new(Token::FileStart, None, 0, 0));
+        for (index, line) in file_str.lines().enumerate() {
+            let line_number = index + 1;
+            if let Some((c, length, indent)) = self.fence {
+                if self.closes((c, length), line) {
+                    self.fence = None;
+                    self.results.push(Info::new(
+                        Token::CodeBlock,
+                        Some(line.to_string()),
+                        re::indent_level(line),
+                        line_number,
+                    ));
+                } else {
+                    // Code is indented relative to its fence
+                    let leading = line.len() - line.trim_start_matches([' ', '\t']).len();
+                    self.results.push(Info::new(
+                        Token::Code,
+                        Some(line[leading.min(indent)..].to_string()),
+                        0,
+                        line_number,
+                    ));
+                }
+                self.number_of_lines += 1;
+                continue;
+            }
             if line.trim().is_empty() {
-                self.results.push(Info::new(Token::Blank, None, 0));
-                continue
+                self.results
+                    .push(Info::new(Token::Blank, None, 0, line_number));
+                continue;
+            }
+            if let Some(code) = self.indented_code(line) {
+                self.results.push(Info::new(
+                    Token::IndentedCode,
+                    Some(code.to_string()),
+                    0,
+                    line_number,
+                ));
+                self.number_of_lines += 1;
+                continue;
             }
 
             let indent_level = re::indent_level(line);
             let line = line.to_string();
             if re::is_heading(&line) {
-                let (level, line) = re::parse_heading(&line);
-                self.results
-                    .push(Info::new(Token::Heading(level), Some(line), indent_level));
+                let (level, contents) = re::parse_heading(&line);
+                let after_marker = line.trim_start()[level..].chars().next();
+                if after_marker.is_some_and(|c| !c.is_whitespace()) {
+                    let column = line.len() - line.trim_start().len() + level + 1;
+                    self.diagnostics.push(
+                        Diagnostic::warning(
+                            line_number,
+                            column,
+                            "missing space after heading marker",
+                        )
+                        .with_hint(format!(
+                            "write `{} {}`",
+                            "#".repeat(level),
+                            contents
+                        )),
+                    );
+                }
+                self.results.push(Info::new(
+                    Token::Heading(level),
+                    Some(contents),
+                    indent_level,
+                    line_number,
+                ));
             } else if re::is_unordered_list(&line) {
                 let line = re::replace_unordered_list(&line);
-                self.results
-                    .push(Info::new(Token::UnorderedList, Some(line), indent_level));
-            } else if re::is_ordered_list(&line) {
-                let (number, line) = re::replace_ordered_list(&line);
                 self.results.push(Info::new(
-                    Token::OrderedList(number),
+                    Token::UnorderedList,
                     Some(line),
                     indent_level,
+                    line_number,
                 ));
-            } else if re::is_code_block(&line) {
+            } else if re::is_ordered_list(&line) {
+                if let Some((number, contents)) = re::replace_ordered_list(&line) {
+                    self.results.push(Info::new(
+                        Token::OrderedList(number),
+                        Some(contents),
+                        indent_level,
+                        line_number,
+                    ));
+                } else {
+                    // Sentences such as "Hello. World" also look like ordered lists,
+                    // so only markers that start out numeric are worth reporting
+                    let marker = line.trim_start();
+                    if marker.starts_with(|c: char| c.is_ascii_digit()) {
+                        let column = line.len() - marker.len() + 1;
+                        self.diagnostics.push(
+                            Diagnostic::warning(line_number, column, "invalid ordered list marker")
+                                .with_hint("use a number or a single letter, e.g. `1.` or `a.`; treating the line as text"),
+                        );
+                    }
+                    self.results.push(Info::new(
+                        Token::Text,
+                        Some(line),
+                        indent_level,
+                        line_number,
+                    ));
+                }
+            } else if let Some((c, length)) = re::code_fence(&line) {
                 self.contains_code_block = true;
-                self.results
-                    .push(Info::new(Token::CodeBlock, Some(line), indent_level));
+                self.fence = Some((c, length, line.len() - line.trim_start().len()));
+                self.results.push(Info::new(
+                    Token::CodeBlock,
+                    Some(line),
+                    indent_level,
+                    line_number,
+                ));
             } else if re::is_block_quote(&line) {
-                self.results
-                    .push(Info::new(Token::BlockQuote, Some(line), indent_level));
+                self.results.push(Info::new(
+                    Token::BlockQuote,
+                    Some(line),
+                    indent_level,
+                    line_number,
+                ));
             } else if re::is_comment(&line) {
-                self.results.push(Info::new(Token::Comment, None, 0));
-            } else if re::is_normal(&line) {
                 self.results
-                    .push(Info::new(Token::Text, Some(line), indent_level));
+                    .push(Info::new(Token::Comment, None, 0, line_number));
+            } else if re::is_normal(&line) || line.trim_start().starts_with('`') {
+                // Fences were matched above, so a backtick starts inline code
+                self.results.push(Info::new(
+                    Token::Text,
+                    Some(line),
+                    indent_level,
+                    line_number,
+                ));
+            } else {
+                let column = line.len() - line.trim_start().len() + 1;
+                self.diagnostics.push(
+                    Diagnostic::warning(line_number, column, "line is ignored").with_hint(
+                        "lines starting with `-`, `+` or `#` must be list items or headings",
+                    ),
+                );
             }
             self.number_of_lines += 1;
         }
-        self.results.push(Info::new(Token::FileEnd, None, 0));
+        let last_line = file_str.lines().count();
+        self.results
+            .push(Info::new(Token::FileEnd, None, 0, last_line));
+    }
+
+    /// The code of a line in an indented code block. Such blocks start after
+    /// a blank line, except in lists, where indentation nests items instead.
+    fn indented_code<'l>(&self, line: &'l str) -> Option<&'l str> {
+        let code = line
+            .strip_prefix("    ")
+            .or_else(|| line.strip_prefix('\t'))?;
+        let mut previous = self.results.iter().rev().map(|info| info.token);
+        let starts = match previous.next() {
+            Some(Token::FileStart) => true,
+            Some(Token::Blank) => {
+                previous
+                    .find(|token| *token != Token::Blank)
+                    .is_some_and(|token| {
+                        !matches!(
+                            token,
+                            Token::UnorderedList | Token::OrderedList(_) | Token::BlockQuote
+                        )
+                    })
+            }
+            _ => false,
+        };
+        let continues = self
+            .results
+            .iter()
+            .rev()
+            .find(|info| info.token != Token::Blank)
+            .is_some_and(|info| info.token == Token::IndentedCode);
+        (starts || continues).then_some(code)
+    }
+
+    /// Whether `line` closes a code block opened by `fence`: the same
+    /// character, at least as many times and nothing after it.
+    fn closes(&self, (c, length): (char, usize), line: &str) -> bool {
+        matches!(re::code_fence(line), Some((close, close_length)) if close == c && close_length >= length)
+            && re::replace_code_block(Some(line)).is_none()
     }
 
     pub fn is_group(kind: &Token) -> bool {
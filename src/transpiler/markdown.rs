@@ -0,0 +1,302 @@
+//! Markdown output, written in the form saurus parses best.
+
+use std::collections::HashSet;
+
+use regex::Regex;
+
+use crate::transpiler::ast::{self, CodeBlock, Heading, Inline, List, ListKind, Quote};
+use crate::transpiler::backend::{Backend, Context, Fragment};
+use crate::transpiler::code_blocks;
+use crate::transpiler::diagnostics::{self, Diagnostic};
+use crate::transpiler::generator::indent_lines;
+use crate::transpiler::lexer::{Lexer, Span};
+use crate::transpiler::{generate_with, parse, Options};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Markdown {
+    /// Rendering the lines of a block quote, which break without `\\`
+    quoted: bool,
+}
+
+impl Backend for Markdown {
+    fn prologue(&mut self, _cx: &mut Context) -> String {
+        String::new()
+    }
+
+    fn epilogue(&mut self, _cx: &mut Context) -> String {
+        String::new()
+    }
+
+    fn comment(&mut self, text: &str) -> String {
+        format!("<!-- {} -->", text)
+    }
+
+    fn heading(&mut self, heading: &Heading, cx: &mut Context) -> Vec<Fragment> {
+        let text = format!(
+            "{} {}",
+            "#".repeat(heading.level.max(1)),
+            single_line(&self.inlines(&heading.content, cx))
+        );
+        vec![Fragment::new(text, heading.span)]
+    }
+
+    fn paragraph(&mut self, paragraph: &ast::Paragraph, cx: &mut Context) -> Vec<Fragment> {
+        let text = self
+            .inlines(&paragraph.content, cx)
+            .lines()
+            .map(escape_line_start)
+            .collect::<Vec<_>>()
+            .join("\n");
+        vec![Fragment::new(text, paragraph.span)]
+    }
+
+    fn list(&mut self, list: &List, cx: &mut Context) -> Vec<Fragment> {
+        let indent = "    ".repeat(cx.depth);
+        let mut fragments = Vec::new();
+        for (index, item) in list.items.iter().enumerate() {
+            let marker = match list.kind {
+                ListKind::Unordered => "-".to_string(),
+                ListKind::Ordered { start } => format!("{}.", start + index),
+            };
+            let line = single_line(&self.inlines(&item.content, cx));
+            fragments.push(Fragment::new(
+                format!("{}{} {}", indent, marker, line),
+                Span::line(item.span.start),
+            ));
+            cx.depth += 1;
+            for child in item.children.iter() {
+                let nested = "    ".repeat(cx.depth);
+                fragments.extend(self.block(child, cx).into_iter().map(|mut fragment| {
+                    if !matches!(child, ast::Block::List(_)) {
+                        fragment.text = indent_lines(&fragment.text, &nested);
+                    }
+                    fragment
+                }));
+            }
+            cx.depth -= 1;
+        }
+        fragments
+    }
+
+    fn quote(&mut self, quote: &Quote, cx: &mut Context) -> Vec<Fragment> {
+        let (depth, quoted) = (cx.depth, self.quoted);
+        cx.depth = 0;
+        self.quoted = true;
+        let mut lines = Vec::new();
+        for block in quote.blocks.iter() {
+            for fragment in self.block(block, cx) {
+                lines.extend(fragment.text.lines().map(|line| format!("> {}", line)));
+            }
+        }
+        cx.depth = depth;
+        self.quoted = quoted;
+        vec![Fragment::new(lines.join("\n"), quote.span)]
+    }
+
+    fn code_block(&mut self, code_block: &CodeBlock, _cx: &mut Context) -> Vec<Fragment> {
+        let fence = fence(&code_block.code);
+        let mut text = format!("{}{}\n", fence, info_string(code_block));
+        if !code_block.code.is_empty() {
+            text.push_str(&code_block.code);
+            text.push('\n');
+        }
+        text.push_str(&fence);
+        vec![Fragment::new(text, code_block.span)]
+    }
+
+    fn text(&mut self, text: &str, _cx: &mut Context) -> String {
+        escape(text)
+    }
+
+    fn bold(&mut self, content: &[Inline], cx: &mut Context) -> String {
+        if let [Inline::Italic(content)] = content {
+            return format!("***{}***", self.inlines(content, cx));
+        }
+        format!("**{}**", self.inlines(content, cx))
+    }
+
+    fn italic(&mut self, content: &[Inline], cx: &mut Context) -> String {
+        format!("*{}*", self.inlines(content, cx))
+    }
+
+    fn strike(&mut self, content: &[Inline], cx: &mut Context) -> String {
+        format!("~~{}~~", self.inlines(content, cx))
+    }
+
+    fn code(&mut self, code: &str, _cx: &mut Context) -> String {
+        format!("`{}`", code)
+    }
+
+    fn link(&mut self, content: &[Inline], url: &str, cx: &mut Context) -> String {
+        format!("[{}]({})", self.inlines(content, cx), url)
+    }
+
+    fn math(&mut self, math: &str, _cx: &mut Context) -> String {
+        format!("${}$", math)
+    }
+
+    fn raw_inline(&mut self, latex: &str, _cx: &mut Context) -> String {
+        latex.to_string()
+    }
+
+    fn line_break(&mut self, _cx: &mut Context) -> String {
+        if self.quoted {
+            "\n".to_string()
+        } else {
+            "\\\\\n".to_string()
+        }
+    }
+}
+
+/// Renders a document as Markdown.
+///
+/// ```
+/// use saurus::transpiler::{markdown, parse};
+///
+/// let source = "# Title\n\n- one\n    1. nested\n\n> quoted\n> lines\n";
+/// assert_eq!(markdown::render(&parse(source, &mut Vec::new()), &mut Vec::new()), source);
+/// ```
+pub fn render(document: &ast::Document, diagnostics: &mut Vec<Diagnostic>) -> String {
+    let options = Options::default();
+    let (text, _) = generate_with(&mut Markdown::default(), document, &options, diagnostics);
+    format!("{}\n", text.trim_end())
+}
+
+/// Formats Markdown into the canonical form saurus parses best: `-` bullets,
+/// lists indented by four spaces, renumbered ordered lists, `*` emphasis and
+/// code fences with their attributes in one form. Code languages are written
+/// as they are in the source, since each code backend reads them its own way.
+///
+/// Comments, and lines saurus ignores, are kept where they are. Returns
+/// `None` if the document has errors, which are pushed onto `diagnostics`
+/// along with any warnings.
+///
+/// ```
+/// use saurus::transpiler::markdown;
+///
+/// let source = "#Title\n+ one\n    * nested\n3) three\n3) four\n<!-- end -->\n";
+/// let formatted = markdown::format(source, &mut Vec::new()).unwrap();
+/// assert_eq!(formatted, "# Title\n\n- one\n    - nested\n\n3. three\n4. four\n\n<!-- end -->\n");
+/// assert_eq!(markdown::format(&formatted, &mut Vec::new()).unwrap(), formatted);
+///
+/// // Neither a language listings lacks nor an alias is replaced, nor blank lines of code
+/// let code = "```zig\nconst x = 1;\n```\n\n```py\nx = 1\n\n```\n";
+/// assert_eq!(markdown::format(code, &mut Vec::new()).unwrap(), code);
+/// ```
+pub fn format(source: &str, diagnostics: &mut Vec<Diagnostic>) -> Option<String> {
+    let mut found = Vec::new();
+    let document = parse(source, &mut found);
+    let failed = diagnostics::has_errors(&found);
+    diagnostics.append(&mut found);
+    if failed {
+        return None;
+    }
+
+    // Lines the lexer made no token for would be lost by rewriting them
+    let mut lexer = Lexer::new();
+    lexer.tokenize(source);
+    let tokenized: HashSet<usize> = lexer.results.iter().map(|info| info.span.start).collect();
+    let lines: Vec<&str> = source.lines().collect();
+    let dropped = |span: Span| {
+        (span.start..=span.end.min(lines.len()))
+            .any(|line| !tokenized.contains(&line) && !lines[line - 1].trim().is_empty())
+    };
+
+    let options = Options::default();
+    let mut cx = Context::new(&options, diagnostics);
+    let mut backend = Markdown::default();
+    let mut chunks: Vec<(usize, String)> = Vec::new();
+    for block in document.blocks.iter() {
+        let span = block.span();
+        let text = if dropped(span) {
+            lines[span.start - 1..span.end].join("\n")
+        } else {
+            backend
+                .block(block, &mut cx)
+                .into_iter()
+                .map(|fragment| fragment.text)
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        chunks.push((span.start, text));
+    }
+    // Lines outside every block are comments, or lines saurus drops
+    for (index, line) in lines.iter().enumerate() {
+        let covered = document
+            .blocks
+            .iter()
+            .any(|block| block.span().contains(index + 1));
+        if !covered && !line.trim().is_empty() {
+            chunks.push((index + 1, line.trim_end().to_string()));
+        }
+    }
+    chunks.sort_by_key(|(line, _)| *line);
+
+    let text = chunks
+        .into_iter()
+        .map(|(_, text)| text)
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Some(format!("{}\n", text))
+}
+
+/// Escapes the characters of plain text that Markdown would read as markup.
+///
+/// ```
+/// use saurus::transpiler::markdown;
+/// assert_eq!(markdown::escape("2 * 3 => [6] for $5"), r"2 \* 3 =\> \[6] for $5");
+/// ```
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut previous = None;
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+        let markup = match c {
+            '*' | '`' | '[' => true,
+            // A dollar sign followed by a digit is already read as currency
+            '$' => !next.is_some_and(|next| next.is_ascii_digit()),
+            '~' => next == Some('~') || previous == Some('~'),
+            _ => false,
+        };
+        if markup {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        previous = Some(c);
+    }
+    escaped.replace("=>", "=\\>").replace("&rarr;", "\\&rarr;")
+}
+
+/// Escapes a line of text that would otherwise start a block.
+fn escape_line_start(line: &str) -> String {
+    let ordered = Regex::new(r"^(\d+|[[:alpha:]])[\.\)]").unwrap();
+    if let Some(marker) = ordered.find(line) {
+        let end = marker.end() - 1;
+        return format!("{}\\{}", &line[..end], &line[end..]);
+    }
+    if line.starts_with(['#', '-', '+', '>']) {
+        return format!("\\{}", line);
+    }
+    line.to_string()
+}
+
+/// A backtick fence longer than any run of backticks in `code`
+fn fence(code: &str) -> String {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in code.chars() {
+        current = if c == '`' { current + 1 } else { 0 };
+        longest = longest.max(current);
+    }
+    "`".repeat(3.max(longest + 1))
+}
+
+fn info_string(code_block: &CodeBlock) -> String {
+    code_blocks::info_string(code_block.language.as_deref(), &code_block.attributes)
+}
+
+/// Joins the lines of rendered inline text with spaces.
+fn single_line(text: &str) -> String {
+    text.lines().map(str::trim).collect::<Vec<_>>().join(" ")
+}
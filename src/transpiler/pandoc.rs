@@ -0,0 +1,585 @@
+//! The document tree as pandoc's JSON AST, so pandoc filters can be run on it.
+//!
+//! ```
+//! use saurus::transpiler::{pandoc, parse};
+//!
+//! let document = parse("# Title\n\nSome **bold** text", &mut Vec::new());
+//! let json = pandoc::to_json(&document);
+//! assert!(json.contains(r#""t":"Strong""#));
+//! assert_eq!(pandoc::from_json(&json, &mut Vec::new()).unwrap().blocks.len(), 2);
+//! ```
+
+use serde_json::{json, Value};
+
+use crate::transpiler::ast::{
+    Block, CodeAttributes, CodeBlock, Document, Heading, Inline, Item, List, ListKind, Paragraph,
+    Quote, RawLatex,
+};
+use crate::transpiler::code_blocks;
+use crate::transpiler::diagnostics::Diagnostic;
+use crate::transpiler::lexer::Span;
+use crate::transpiler::Error;
+
+/// The version of pandoc-types whose JSON is written
+const API_VERSION: [u32; 3] = [1, 23, 1];
+
+/// Serializes a document as pandoc JSON.
+pub fn to_json(document: &Document) -> String {
+    Writer { spans: false }.document(document)
+}
+
+/// Like [`to_json`], but records the Markdown lines of each block in a
+/// `data-pos` attribute, as pandoc's `sourcepos` extension does, wrapping
+/// blocks without attributes in a `Div`. [`from_json`] reads them back, so
+/// spans survive a filter that keeps the attributes.
+///
+/// ```
+/// use saurus::transpiler::{pandoc, parse};
+///
+/// let source = "# Title\n\nSome\ntext\n\n- one\n    - two\n\n> quoted\n\n```rust\nfn main() {}\n```";
+/// let document = parse(source, &mut Vec::new());
+/// let json = pandoc::to_json_with_spans(&document);
+/// assert!(json.contains(r#"["data-pos","3:1-5:1"]"#));
+/// assert_eq!(pandoc::from_json(&json, &mut Vec::new()).unwrap(), document);
+/// ```
+pub fn to_json_with_spans(document: &Document) -> String {
+    Writer { spans: true }.document(document)
+}
+
+struct Writer {
+    /// Record the spans of blocks
+    spans: bool,
+}
+
+impl Writer {
+    fn document(&self, document: &Document) -> String {
+        let value = json!({
+            "pandoc-api-version": API_VERSION,
+            "meta": {},
+            "blocks": self.blocks(&document.blocks),
+        });
+        value.to_string()
+    }
+
+    fn blocks(&self, blocks: &[Block]) -> Vec<Value> {
+        blocks.iter().map(|block| self.block(block)).collect()
+    }
+
+    fn block(&self, block: &Block) -> Value {
+        let value = match block {
+            Block::Heading(heading) => {
+                return element(
+                    "Header",
+                    json!([
+                        heading.level,
+                        self.positioned(attr(None), heading.span),
+                        inlines(&heading.content)
+                    ]),
+                )
+            }
+            Block::CodeBlock(code_block) => {
+                return element(
+                    "CodeBlock",
+                    json!([
+                        self.positioned(code_attr(code_block), code_block.span),
+                        code_block.code
+                    ]),
+                )
+            }
+            Block::Paragraph(paragraph) => element("Para", json!(inlines(&paragraph.content))),
+            Block::List(list) => {
+                let items: Vec<Value> = list.items.iter().map(|item| self.item(item)).collect();
+                match list.kind {
+                    ListKind::Unordered => element("BulletList", json!(items)),
+                    ListKind::Ordered { start } => element(
+                        "OrderedList",
+                        json!([
+                            [start, { "t": "Decimal" }, { "t": "Period" }],
+                            items
+                        ]),
+                    ),
+                }
+            }
+            Block::Quote(quote) => element("BlockQuote", json!(self.blocks(&quote.blocks))),
+            Block::RawLatex(raw) => element("RawBlock", json!(["latex", raw.latex])),
+        };
+        self.wrapped(value, block.span())
+    }
+
+    /// An item's text as a `Plain` block followed by its children
+    fn item(&self, item: &Item) -> Value {
+        let plain = element("Plain", json!(inlines(&item.content)));
+        let mut item_blocks = vec![self.wrapped(plain, item.span)];
+        item_blocks.extend(self.blocks(&item.children));
+        json!(item_blocks)
+    }
+
+    /// `attr` along with the `data-pos` of `span`
+    fn positioned(&self, mut attr: Value, span: Span) -> Value {
+        if self.spans && span.start > 0 {
+            if let Some(pairs) = attr.get_mut(2).and_then(Value::as_array_mut) {
+                pairs.push(json!(["data-pos", position(span)]));
+            }
+        }
+        attr
+    }
+
+    /// `block` in a `Div` with the `data-pos` of `span`
+    fn wrapped(&self, block: Value, span: Span) -> Value {
+        if !self.spans || span.start == 0 {
+            return block;
+        }
+        element("Div", json!([self.positioned(attr(None), span), [block]]))
+    }
+}
+
+/// A span the way pandoc writes source positions, from the first line up to
+/// the start of the line after the last
+fn position(span: Span) -> String {
+    format!("{}:1-{}:1", span.start, span.end + 1)
+}
+
+/// Reads a `data-pos` such as `3:1-5:1` or `notes.md@3:1-4:12` as a span.
+fn parse_position(position: &str) -> Option<Span> {
+    let position = position.rsplit('@').next()?;
+    let (start, end) = position.split_once('-')?;
+    let line = |position: &str| -> Option<(usize, usize)> {
+        let (line, column) = position.split_once(':')?;
+        Some((line.parse().ok()?, column.parse().ok()?))
+    };
+    let ((start, _), (end, column)) = (line(start)?, line(end)?);
+    // An end at the start of a line is just after the block
+    let end = if column <= 1 && end > start {
+        end - 1
+    } else {
+        end
+    };
+    (0 < start && start <= end).then_some(Span::new(start, end))
+}
+
+/// The span in the `data-pos` of an `Attr`, if it has one
+fn attr_span(attr: &Value) -> Option<Span> {
+    attr.get(2)?.as_array()?.iter().find_map(|pair| {
+        match (pair.get(0)?.as_str()?, pair.get(1)?.as_str()?) {
+            ("data-pos", position) => parse_position(position),
+            _ => None,
+        }
+    })
+}
+
+fn set_span(block: &mut Block, span: Span) {
+    let current = match block {
+        Block::Heading(heading) => &mut heading.span,
+        Block::Paragraph(paragraph) => &mut paragraph.span,
+        Block::List(list) => &mut list.span,
+        Block::Quote(quote) => &mut quote.span,
+        Block::CodeBlock(code_block) => &mut code_block.span,
+        Block::RawLatex(raw) => &mut raw.span,
+    };
+    if *current == Span::default() {
+        *current = span;
+    }
+}
+
+fn inlines(inlines: &[Inline]) -> Vec<Value> {
+    let mut values = Vec::new();
+    for inline in inlines {
+        match inline {
+            // Pandoc keeps words and the spaces between them apart
+            Inline::Text(text) => {
+                for (index, word) in text.split(' ').enumerate() {
+                    if index > 0 {
+                        values.push(json!({ "t": "Space" }));
+                    }
+                    if !word.is_empty() {
+                        values.push(element("Str", json!(word)));
+                    }
+                }
+            }
+            Inline::Bold(content) => values.push(element("Strong", json!(self::inlines(content)))),
+            Inline::Italic(content) => values.push(element("Emph", json!(self::inlines(content)))),
+            Inline::Strike(content) => {
+                values.push(element("Strikeout", json!(self::inlines(content))))
+            }
+            Inline::Code(code) => values.push(element("Code", json!([attr(None), code]))),
+            Inline::Link { content, url } => values.push(element(
+                "Link",
+                json!([attr(None), self::inlines(content), [url, ""]]),
+            )),
+            Inline::Math(math) => {
+                values.push(element("Math", json!([{ "t": "InlineMath" }, math])))
+            }
+            Inline::RawLatex(latex) => values.push(element("RawInline", json!(["latex", latex]))),
+            Inline::SoftBreak => values.push(json!({ "t": "SoftBreak" })),
+            Inline::LineBreak => values.push(json!({ "t": "LineBreak" })),
+        }
+    }
+    values
+}
+
+fn element(tag: &str, content: Value) -> Value {
+    json!({ "t": tag, "c": content })
+}
+
+/// An `Attr` with no id, the language as its only class, and no attributes
+fn attr(language: Option<&str>) -> Value {
+    json!(["", language.into_iter().collect::<Vec<_>>(), []])
+}
+
+/// A code block's attributes the way pandoc writes them for
+/// `{.python .numberLines #lst:x startFrom="10"}`
+fn code_attr(code_block: &CodeBlock) -> Value {
+    let attributes = &code_block.attributes;
+    let mut classes: Vec<&str> = code_block.language.iter().map(String::as_str).collect();
+    if attributes.line_numbers {
+        classes.push("numberLines");
+    }
+    let mut pairs = Vec::new();
+    if let Some(caption) = &attributes.caption {
+        pairs.push(json!(["caption", caption]));
+    }
+    if let Some(first_line) = attributes.first_line {
+        pairs.push(json!(["startFrom", first_line.to_string()]));
+    }
+    if !attributes.highlight.is_empty() {
+        pairs.push(json!([
+            "hl",
+            code_blocks::ranges_string(&attributes.highlight)
+        ]));
+    }
+    if let Some(file) = &attributes.file {
+        pairs.push(json!(["file", file]));
+    }
+    if let Some(lines) = attributes.lines {
+        pairs.push(json!(["lines", code_blocks::ranges_string(&[lines])]));
+    }
+    if let Some(style) = &attributes.style {
+        pairs.push(json!(["style", style]));
+    }
+    json!([
+        attributes.label.as_deref().unwrap_or_default(),
+        classes,
+        pairs
+    ])
+}
+
+/// Reads a document from pandoc JSON.
+///
+/// Elements saurus has no equivalent for are dropped with a warning, or
+/// reduced to their contents where they have any. Blocks only have spans if
+/// they have a `data-pos`, like those of [`to_json_with_spans`]. Raw LaTeX
+/// is written into LaTeX output as is:
+///
+/// ```
+/// use saurus::transpiler::{generate, pandoc, Options};
+///
+/// let json = r#"{"blocks": [{"t": "Para", "c": [
+///     {"t": "Str", "c": "See"}, {"t": "Space"},
+///     {"t": "RawInline", "c": ["latex", "\\ref{fig_a}"]}
+/// ]}]}"#;
+/// let document = pandoc::from_json(json, &mut Vec::new()).unwrap();
+/// let (tex, _) = generate(&document, &Options::default(), &mut Vec::new());
+/// assert!(tex.contains(r"See \ref{fig_a}"));
+/// ```
+pub fn from_json(json: &str, diagnostics: &mut Vec<Diagnostic>) -> Result<Document, Error> {
+    let value: Value = serde_json::from_str(json)
+        .map_err(|error| Error::Pandoc(format!("invalid JSON: {}", error)))?;
+    let blocks = value
+        .get("blocks")
+        .ok_or_else(|| invalid("the document has no `blocks`"))?;
+    let mut reader = Reader { diagnostics };
+    Ok(Document {
+        blocks: reader.blocks(blocks)?,
+    })
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::Pandoc(format!("invalid pandoc document: {}", message.into()))
+}
+
+struct Reader<'a> {
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a> Reader<'a> {
+    /// Reads pandoc's `[id, classes, pairs]` like the attributes of a fence.
+    fn code_attributes(
+        &mut self,
+        attributes: &Value,
+    ) -> Result<(Option<String>, CodeAttributes), Error> {
+        let [id, classes, pairs] = fields(attributes, "CodeBlock attributes")?;
+        let mut words = Vec::new();
+        let id = string(id, "an identifier")?;
+        if !id.is_empty() {
+            words.push(format!("#{}", id));
+        }
+        for class in array(classes, "a list of classes")? {
+            words.push(format!(".{}", string(class, "a class")?));
+        }
+        for pair in array(pairs, "a list of attributes")? {
+            let [key, value] = fields(pair, "an attribute")?;
+            let (key, value) = (string(key, "a key")?, string(value, "a value")?);
+            if key == "data-pos" {
+                continue;
+            }
+            words.push(format!("{}=\"{}\"", key, value));
+        }
+        Ok(code_blocks::parse_info(
+            &words.join(" "),
+            0,
+            0,
+            self.diagnostics,
+        ))
+    }
+
+    fn unsupported(&mut self, tag: &str, kept: &str) {
+        let diagnostic = Diagnostic::warning(0, 0, format!("unsupported pandoc element `{}`", tag))
+            .with_hint(kept.to_string());
+        if !self.diagnostics.contains(&diagnostic) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn blocks(&mut self, value: &Value) -> Result<Vec<Block>, Error> {
+        let mut blocks = Vec::new();
+        for block in array(value, "a list of blocks")? {
+            self.block(block, &mut blocks)?;
+        }
+        Ok(blocks)
+    }
+
+    /// Reads `value` into zero or more blocks pushed onto `blocks`
+    fn block(&mut self, value: &Value, blocks: &mut Vec<Block>) -> Result<(), Error> {
+        let (tag, content) = tagged(value)?;
+        match tag {
+            "Plain" | "Para" => blocks.push(Block::Paragraph(Paragraph {
+                content: self.inlines(content)?,
+                span: Span::default(),
+            })),
+            "Header" => {
+                let [level, attr, content] = fields(content, "Header")?;
+                let level = level
+                    .as_u64()
+                    .ok_or_else(|| invalid("a `Header` level is not a number"))?;
+                blocks.push(Block::Heading(Heading {
+                    level: level as usize,
+                    content: self.inlines(content)?,
+                    span: attr_span(attr).unwrap_or_default(),
+                }));
+            }
+            "BulletList" => blocks.push(Block::List(List {
+                kind: ListKind::Unordered,
+                items: self.items(content)?,
+                span: Span::default(),
+            })),
+            "OrderedList" => {
+                let [attributes, items] = fields(content, "OrderedList")?;
+                let start = attributes
+                    .get(0)
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| invalid("an `OrderedList` has no start number"))?;
+                blocks.push(Block::List(List {
+                    kind: ListKind::Ordered {
+                        start: start as usize,
+                    },
+                    items: self.items(items)?,
+                    span: Span::default(),
+                }));
+            }
+            "BlockQuote" => blocks.push(Block::Quote(Quote {
+                blocks: self.blocks(content)?,
+                span: Span::default(),
+            })),
+            "CodeBlock" => {
+                let [attr, code] = fields(content, "CodeBlock")?;
+                let (language, attributes) = self.code_attributes(attr)?;
+                blocks.push(Block::CodeBlock(CodeBlock {
+                    language,
+                    attributes,
+                    code: string(code, "`CodeBlock` code")?,
+                    span: attr_span(attr).unwrap_or_default(),
+                }));
+            }
+            "Div" => {
+                let [attr, content] = fields(content, "Div")?;
+                let mut contents = self.blocks(content)?;
+                if let Some(span) = attr_span(attr) {
+                    for block in contents.iter_mut() {
+                        set_span(block, span);
+                    }
+                }
+                blocks.extend(contents);
+            }
+            "LineBlock" => {
+                let mut inlines = Vec::new();
+                for (index, line) in array(content, "a list of lines")?.iter().enumerate() {
+                    if index > 0 {
+                        inlines.push(Inline::LineBreak);
+                    }
+                    inlines.extend(self.inlines(line)?);
+                }
+                blocks.push(Block::Paragraph(Paragraph {
+                    content: inlines,
+                    span: Span::default(),
+                }));
+            }
+            "RawBlock" => {
+                let [format, text] = fields(content, "RawBlock")?;
+                if matches!(format.as_str(), Some("latex" | "tex")) {
+                    blocks.push(Block::RawLatex(RawLatex {
+                        latex: string(text, "`RawBlock` text")?,
+                        span: Span::default(),
+                    }));
+                } else {
+                    self.unsupported(tag, "raw blocks of formats other than LaTeX are dropped");
+                }
+            }
+            _ => self.unsupported(tag, "it was dropped"),
+        }
+        Ok(())
+    }
+
+    /// List items, with a leading `Plain` or `Para` as each item's text
+    fn items(&mut self, value: &Value) -> Result<Vec<Item>, Error> {
+        let mut items = Vec::new();
+        for item in array(value, "a list of items")? {
+            let mut children = self.blocks(item)?;
+            let span = match children.first() {
+                Some(Block::Paragraph(paragraph)) => paragraph.span,
+                _ => Span::default(),
+            };
+            let content = match children.first() {
+                Some(Block::Paragraph(_)) => match children.remove(0) {
+                    Block::Paragraph(paragraph) => paragraph.content,
+                    _ => unreachable!(),
+                },
+                _ => Vec::new(),
+            };
+            items.push(Item {
+                content,
+                children,
+                span,
+            });
+        }
+        Ok(items)
+    }
+
+    fn inlines(&mut self, value: &Value) -> Result<Vec<Inline>, Error> {
+        let mut inlines = Vec::new();
+        for inline in array(value, "a list of inlines")? {
+            self.inline(inline, &mut inlines)?;
+        }
+        Ok(inlines)
+    }
+
+    /// Reads `value` onto `inlines`, merging adjacent text
+    fn inline(&mut self, value: &Value, inlines: &mut Vec<Inline>) -> Result<(), Error> {
+        let (tag, content) = tagged(value)?;
+        let inline = match tag {
+            "Str" => Inline::Text(string(content, "`Str` text")?),
+            "Space" => Inline::Text(" ".to_string()),
+            "SoftBreak" => Inline::SoftBreak,
+            "LineBreak" => Inline::LineBreak,
+            "Strong" => Inline::Bold(self.inlines(content)?),
+            "Emph" => Inline::Italic(self.inlines(content)?),
+            "Strikeout" => Inline::Strike(self.inlines(content)?),
+            "Code" => {
+                let [_, code] = fields(content, "Code")?;
+                Inline::Code(string(code, "`Code` text")?)
+            }
+            "Math" => {
+                let [_, math] = fields(content, "Math")?;
+                Inline::Math(string(math, "`Math` text")?)
+            }
+            "Link" | "Image" => {
+                if tag == "Image" {
+                    self.unsupported(tag, "it is kept as a link to the image");
+                }
+                let [_, content, target] = fields(content, tag)?;
+                let url = target
+                    .get(0)
+                    .ok_or_else(|| invalid(format!("a `{}` has no target", tag)))?;
+                Inline::Link {
+                    content: self.inlines(content)?,
+                    url: string(url, "a link target")?,
+                }
+            }
+            "Quoted" => {
+                let [kind, content] = fields(content, "Quoted")?;
+                let (open, close) = match kind.get("t").and_then(Value::as_str) {
+                    Some("SingleQuote") => ("`", "'"),
+                    _ => ("``", "''"),
+                };
+                inlines.push(Inline::Text(open.to_string()));
+                for inline in array(content, "a list of inlines")? {
+                    self.inline(inline, inlines)?;
+                }
+                Inline::Text(close.to_string())
+            }
+            "RawInline" => {
+                let [format, text] = fields(content, "RawInline")?;
+                if !matches!(format.as_str(), Some("latex" | "tex")) {
+                    self.unsupported(tag, "raw inlines of formats other than LaTeX are dropped");
+                    return Ok(());
+                }
+                Inline::RawLatex(string(text, "`RawInline` text")?)
+            }
+            // Formatting saurus cannot express keeps just its text
+            "Underline" | "SmallCaps" | "Superscript" | "Subscript" => {
+                self.unsupported(tag, "only its text is kept");
+                for inline in array(content, "a list of inlines")? {
+                    self.inline(inline, inlines)?;
+                }
+                return Ok(());
+            }
+            "Span" | "Cite" => {
+                let [_, content] = fields(content, tag)?;
+                for inline in array(content, "a list of inlines")? {
+                    self.inline(inline, inlines)?;
+                }
+                return Ok(());
+            }
+            _ => {
+                self.unsupported(tag, "it was dropped");
+                return Ok(());
+            }
+        };
+
+        match (inlines.last_mut(), inline) {
+            (Some(Inline::Text(text)), Inline::Text(more)) => text.push_str(&more),
+            (_, inline) => inlines.push(inline),
+        }
+        Ok(())
+    }
+}
+
+/// The `t` and `c` of an element
+fn tagged(value: &Value) -> Result<(&str, &Value), Error> {
+    let tag = value
+        .get("t")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid(format!("expected an element, found `{}`", value)))?;
+    Ok((tag, value.get("c").unwrap_or(&Value::Null)))
+}
+
+fn array<'v>(value: &'v Value, expected: &str) -> Result<&'v Vec<Value>, Error> {
+    value
+        .as_array()
+        .ok_or_else(|| invalid(format!("expected {}, found `{}`", expected, value)))
+}
+
+/// The contents of an element holding exactly `N` fields
+fn fields<'v, const N: usize>(value: &'v Value, tag: &str) -> Result<[&'v Value; N], Error> {
+    let values = array(value, &format!("the fields of `{}`", tag))?;
+    let fields: Vec<&Value> = values.iter().collect();
+    fields
+        .try_into()
+        .map_err(|_| invalid(format!("`{}` should have {} fields", tag, N)))
+}
+
+fn string(value: &Value, what: &str) -> Result<String, Error> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| invalid(format!("{} is not a string", what)))
+}
@@ -1,11 +1,20 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::mem;
 
+use crate::transpiler::ast::{
+    Block, CodeAttributes, CodeBlock, Document, Heading, Inline, Item, List, ListKind, Paragraph,
+    Quote,
+};
 use crate::transpiler::code_blocks;
+use crate::transpiler::diagnostics::Diagnostic;
+use crate::transpiler::inline;
 use crate::transpiler::lexer;
-use crate::transpiler::lexer::Token;
+use crate::transpiler::lexer::{Span, Token};
 use crate::transpiler::re;
 
 use itertools::Itertools;
+use serde::Serialize;
 
 #[derive(Debug)]
 pub struct Parser {
@@ -13,14 +22,20 @@ pub struct Parser {
     pub results: Vec<Contents>,
     pub previous: Option<Record>,
     pub contains_code_block: bool,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Line of the currently open code fence, if any
+    open_code_block: Option<usize>,
+    /// The attributes of each code block, by the line of its opening fence
+    code_attributes: HashMap<usize, CodeAttributes>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Contents {
     pub line: Option<String>,
     pub kind: lexer::Token,
     pub indent_level: usize,
     pub chron: Chronology,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -28,9 +43,10 @@ pub struct Record {
     pub kind: lexer::Token,
     pub chron: Chronology,
     pub indent_level: usize,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum Chronology {
     Start,
     Middle,
@@ -44,10 +60,30 @@ impl From<&Contents> for Record {
             kind: contents.kind,
             chron: contents.chron,
             indent_level: contents.indent_level,
+            span: contents.span,
         }
     }
 }
 
+/// One record per line: its span, place in its group, kind, indent level
+/// and contents.
+impl fmt::Display for Contents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>7}  {:<6}  {:?}  {}",
+            self.span.to_string(),
+            format!("{:?}", self.chron),
+            self.kind,
+            self.indent_level
+        )?;
+        if let Some(line) = &self.line {
+            write!(f, "  {:?}", line)?;
+        }
+        Ok(())
+    }
+}
+
 impl Contents {
     fn new(info: lexer::Info, chron: Chronology) -> Self {
         Contents {
@@ -55,6 +91,7 @@ impl Contents {
             kind: info.token,
             indent_level: info.indent_level,
             chron,
+            span: info.span,
         }
     }
 
@@ -64,10 +101,17 @@ impl Contents {
             kind: info.token,
             indent_level: info.indent_level,
             chron,
+            span: info.span,
         }
     }
 }
 
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Parser {
     pub fn new() -> Parser {
         let records: Vec<Record> = Vec::new();
@@ -75,6 +119,7 @@ impl Parser {
             kind: Token::FileStart,
             chron: Chronology::None,
             indent_level: 0,
+            span: Span::default(),
         });
         let results: Vec<Contents> = Vec::new();
         let contains_code_block = false;
@@ -83,6 +128,9 @@ impl Parser {
             results,
             previous,
             contains_code_block,
+            diagnostics: Vec::new(),
+            open_code_block: None,
+            code_attributes: HashMap::new(),
         }
     }
 
@@ -95,11 +143,16 @@ impl Parser {
             self.contains_code_block = true;
         }
 
+        let last_line = lexer.results.last().map_or(0, |info| info.span.end);
         let mut iter = lexer.results.into_iter().enumerate().multipeek();
         while let Some(item) = iter.next() {
             let (_number, current) = item;
 
-            if current.token == Token::Blank {
+            // Code belongs to the fence that opened it, whatever it looks like
+            if matches!(
+                current.token,
+                Token::Blank | Token::Code | Token::IndentedCode
+            ) {
                 self.results.push(Contents::new(current, Chronology::None));
             } else if current.token == Token::Comment {
                 // Will not allow comment to break when used within nested list.
@@ -107,6 +160,7 @@ impl Parser {
                 self.close_open_blocks();
                 self.records.pop();
                 self.previous = None;
+                self.results.push(Contents::new(current, Chronology::None));
             } else {
                 // Peek through iterator until we reach a non-blank line
                 let next: Option<&lexer::Info> = {
@@ -114,15 +168,13 @@ impl Parser {
                     loop {
                         if let Some((_, next)) = to_return {
                             if next.token != Token::Blank {
-                                break Some(next)
-                            }
-                            else {
+                                break Some(next);
+                            } else {
                                 to_return = iter.peek();
                             }
                         } else {
-                            break None
+                            break None;
                         }
-
                     }
                 };
 
@@ -151,51 +203,65 @@ impl Parser {
             }
         }
         self.close_open_blocks();
+        if let Some(line_number) = self.open_code_block {
+            self.diagnostics.push(
+                Diagnostic::warning(line_number, 1, "unclosed code fence")
+                    .with_hint("the code block is closed at the end of the file"),
+            );
+        }
         self.results.push(Contents {
             line: None,
             kind: Token::FileEnd,
             indent_level: 0,
             chron: Chronology::None,
+            span: Span::line(last_line),
         });
     }
 
     fn close_open_blocks(&mut self) {
+        // Open blocks run up to the last line that was parsed
+        let end = self.previous.as_ref().map(|previous| previous.span.end);
         for record in self.records.iter() {
             self.results.push(Contents {
                 line: None,
                 kind: record.kind,
                 chron: Chronology::End,
                 indent_level: record.indent_level,
+                span: Span::new(record.span.start, end.unwrap_or(record.span.end)),
             });
         }
     }
 
-    fn group_to_contents(&self, current: lexer::Info, next: &lexer::Info) -> Option<Contents> {
-
+    fn group_to_contents(&mut self, current: lexer::Info, next: &lexer::Info) -> Option<Contents> {
+        // Fences open or close a block whatever came before them, even a comment
+        if let Token::CodeBlock = current.token {
+            // The lexer only makes tokens of fences that open or close a block
+            if self.open_code_block.is_none() {
+                let info = re::replace_code_block(current.line.as_deref()).unwrap_or_default();
+                let (language, attributes) = code_blocks::parse_info(
+                    &info,
+                    current.span.start,
+                    fence_column(&current),
+                    &mut self.diagnostics,
+                );
+                self.code_attributes.insert(current.span.start, attributes);
+                self.open_code_block = Some(current.span.start);
+                return Some(Contents::new_with_line(
+                    language,
+                    current,
+                    Chronology::Start,
+                ));
+            } else {
+                self.open_code_block = None;
+                return Some(Contents::new(current, Chronology::End));
+            }
+        }
         if let Some(previous) = &self.previous {
             let token_discrim = mem::discriminant(&current.token);
             let next_discrim = mem::discriminant(&next.token);
             let prev_discrim = mem::discriminant(&previous.kind);
 
-            if let Token::CodeBlock = current.token {
-                if let Some(language) = re::replace_code_block(current.line.as_ref().map(|x| &**x)) {
-                    let mut language = language;
-                    if code_blocks::is_invalid_language(&language) {
-                        eprintln!(
-                            "Language \"{}\" not found. Using default of \"python\".",
-                            language
-                        );
-                        language = "python".to_string();
-                    };
-                    return Some(Contents::new_with_line(
-                        Some(language),
-                        current,
-                        Chronology::Start,
-                    ));
-                } else {
-                    return Some(Contents::new(current, Chronology::End));
-                }
-            } else if current.indent_level > previous.indent_level
+            if current.indent_level > previous.indent_level
                 && current.indent_level > next.indent_level
             {
                 return Some(Contents::new(current, Chronology::None));
@@ -217,7 +283,273 @@ impl Parser {
             None
         } else {
             // No Previous item to consider (must have been erased by a comment), so start anew
-            return Some(Contents::new(current, Chronology::Start));
+            Some(Contents::new(current, Chronology::Start))
+        }
+    }
+}
+
+impl Parser {
+    /// Folds the flat `results` into a document tree.
+    pub fn document(&self) -> Document {
+        let mut builder = TreeBuilder {
+            code_attributes: self.code_attributes.clone(),
+            ..TreeBuilder::default()
+        };
+        for contents in self.results.iter() {
+            builder.push(contents);
+        }
+        builder.finish()
+    }
+}
+
+/// The column just after the backticks or tildes of a code fence
+fn fence_column(fence: &lexer::Info) -> usize {
+    let line = fence.line.as_deref().unwrap_or_default();
+    let indent = line.len() - line.trim_start().len();
+    indent + re::code_fence(line).map_or(0, |(_, length)| length) + 1
+}
+
+/// Rebuilds the nesting of `results`. Lists nest by indent level and, like
+/// quotes, stay open until other content or a comment interrupts them.
+#[derive(Default)]
+struct TreeBuilder {
+    blocks: Vec<Block>,
+    /// Open lists with their indent level, innermost last
+    lists: Vec<(usize, List)>,
+    paragraph: Option<(Vec<String>, Span)>,
+    quote: Option<(Vec<String>, Span)>,
+    code: Option<(Option<String>, Vec<String>, Span)>,
+    code_attributes: HashMap<usize, CodeAttributes>,
+    indented_code: Option<(Vec<String>, Span)>,
+}
+
+impl TreeBuilder {
+    fn push(&mut self, contents: &Contents) {
+        if let Some((_, lines, span)) = &mut self.code {
+            match (&contents.kind, &contents.line) {
+                (Token::CodeBlock, Some(_)) if contents.chron == Chronology::End => {
+                    span.end = contents.span.end;
+                    self.close_code();
+                }
+                (Token::Blank, _) => lines.push(String::new()),
+                // Blank lines after an unclosed block's code are not part of it
+                (Token::FileEnd, _) => {
+                    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+                        lines.pop();
+                    }
+                    self.close_all();
+                }
+                (_, Some(line)) => {
+                    lines.push(line.to_string());
+                    span.end = contents.span.end;
+                }
+                // Blocks closed because the code interrupted them
+                (_, None) => {}
+            }
+            return;
+        }
+        if !matches!(contents.kind, Token::IndentedCode | Token::Blank) {
+            self.close_indented_code();
+        }
+
+        match contents.kind {
+            Token::FileStart | Token::FileEnd | Token::Comment => self.close_all(),
+            Token::Blank => {
+                self.close_paragraph();
+                if let Some((lines, _)) = &mut self.indented_code {
+                    lines.push(String::new());
+                }
+            }
+            // Only ever found inside the code blocks handled above
+            Token::Code => {}
+            Token::IndentedCode => {
+                let line = contents.line.clone().unwrap_or_default();
+                match &mut self.indented_code {
+                    Some((lines, span)) => {
+                        lines.push(line);
+                        span.end = contents.span.end;
+                    }
+                    None => {
+                        self.close_all();
+                        self.indented_code = Some((vec![line], contents.span));
+                    }
+                }
+            }
+            Token::Heading(level) => {
+                self.close_all();
+                let content = inline::parse(contents.line.as_deref().unwrap_or_default());
+                self.blocks.push(Block::Heading(Heading {
+                    level,
+                    content,
+                    span: contents.span,
+                }));
+            }
+            Token::Text => {
+                self.close_lists();
+                self.close_quote();
+                let line = contents.line.clone().unwrap_or_default();
+                match &mut self.paragraph {
+                    Some((lines, span)) => {
+                        lines.push(line);
+                        span.end = contents.span.end;
+                    }
+                    None => self.paragraph = Some((vec![line], contents.span)),
+                }
+            }
+            Token::UnorderedList | Token::OrderedList(_) => {
+                // Interrupted lists are closed by whatever interrupted them
+                if contents.line.is_some() {
+                    self.close_paragraph();
+                    self.close_quote();
+                    self.push_item(contents);
+                }
+            }
+            Token::BlockQuote => {
+                if contents.line.is_none() {
+                    return;
+                }
+                self.close_paragraph();
+                self.close_lists();
+                let raw = contents.line.as_deref().unwrap_or_default();
+                let line = re::replace_block_quote(raw).unwrap_or_else(|| raw.to_string());
+                match &mut self.quote {
+                    Some((lines, span)) => {
+                        lines.push(line);
+                        span.end = contents.span.end;
+                    }
+                    None => self.quote = Some((vec![line], contents.span)),
+                }
+            }
+            Token::CodeBlock => {
+                if contents.chron == Chronology::Start {
+                    self.close_all();
+                    self.code = Some((contents.line.clone(), Vec::new(), contents.span));
+                }
+            }
+        }
+    }
+
+    fn push_item(&mut self, contents: &Contents) {
+        let indent = contents.indent_level;
+        let kind = match contents.kind {
+            Token::OrderedList(start) => ListKind::Ordered { start },
+            _ => ListKind::Unordered,
+        };
+
+        while self.lists.last().is_some_and(|(level, _)| *level > indent) {
+            self.close_list();
+        }
+        let continues = self.lists.last().is_some_and(|(level, list)| {
+            *level == indent && mem::discriminant(&list.kind) == mem::discriminant(&kind)
+        });
+        if !continues {
+            if self.lists.last().is_some_and(|(level, _)| *level == indent) {
+                self.close_list();
+            }
+            let list = List {
+                kind,
+                items: Vec::new(),
+                span: contents.span,
+            };
+            self.lists.push((indent, list));
+        }
+
+        if let Some((_, list)) = self.lists.last_mut() {
+            list.items.push(Item {
+                content: inline::parse(contents.line.as_deref().unwrap_or_default()),
+                children: Vec::new(),
+                span: contents.span,
+            });
+            list.span.end = contents.span.end;
+        }
+    }
+
+    fn close_list(&mut self) {
+        if let Some((_, list)) = self.lists.pop() {
+            let end = list.span.end;
+            let block = Block::List(list);
+            match self.lists.last_mut() {
+                Some((_, parent)) => {
+                    parent.span.end = parent.span.end.max(end);
+                    match parent.items.last_mut() {
+                        Some(item) => {
+                            item.span.end = item.span.end.max(end);
+                            item.children.push(block);
+                        }
+                        None => self.blocks.push(block),
+                    }
+                }
+                None => self.blocks.push(block),
+            }
+        }
+    }
+
+    fn close_lists(&mut self) {
+        while !self.lists.is_empty() {
+            self.close_list();
+        }
+    }
+
+    fn close_paragraph(&mut self) {
+        if let Some((lines, span)) = self.paragraph.take() {
+            let content = inline::parse_lines(lines.iter().map(String::as_str));
+            self.blocks
+                .push(Block::Paragraph(Paragraph { content, span }));
+        }
+    }
+
+    fn close_quote(&mut self) {
+        if let Some((lines, span)) = self.quote.take() {
+            let mut content = Vec::new();
+            for (index, line) in lines.iter().enumerate() {
+                if index > 0 {
+                    content.push(Inline::LineBreak);
+                }
+                content.extend(inline::parse(line));
+            }
+            let blocks = vec![Block::Paragraph(Paragraph { content, span })];
+            self.blocks.push(Block::Quote(Quote { blocks, span }));
+        }
+    }
+
+    fn close_code(&mut self) {
+        if let Some((language, lines, span)) = self.code.take() {
+            self.blocks.push(Block::CodeBlock(CodeBlock {
+                language,
+                attributes: self.code_attributes.remove(&span.start).unwrap_or_default(),
+                code: lines.join("\n"),
+                span,
+            }));
+        }
+    }
+
+    fn close_indented_code(&mut self) {
+        if let Some((mut lines, span)) = self.indented_code.take() {
+            // Blank lines only belong to the code between its lines
+            while lines.last().is_some_and(String::is_empty) {
+                lines.pop();
+            }
+            self.blocks.push(Block::CodeBlock(CodeBlock {
+                language: None,
+                attributes: CodeAttributes::default(),
+                code: lines.join("\n"),
+                span,
+            }));
+        }
+    }
+
+    fn close_all(&mut self) {
+        self.close_paragraph();
+        self.close_lists();
+        self.close_quote();
+        self.close_code();
+        self.close_indented_code();
+    }
+
+    fn finish(mut self) -> Document {
+        self.close_all();
+        Document {
+            blocks: self.blocks,
         }
     }
 }
@@ -1,4 +1,3 @@
-use regex::Captures;
 use regex::Regex;
 
 /// ```
@@ -38,15 +37,15 @@ pub fn is_normal(line: &str) -> bool {
 /// let (number4, contents4) = re::parse_heading(r"#### Heading 4");
 /// assert_eq!(number4, 4);
 /// assert_eq!(contents4, "Heading 4");
+///
+/// let (number2, contents2) = re::parse_heading(r"##Heading 2");
+/// assert_eq!(number2, 2);
+/// assert_eq!(contents2, "Heading 2");
 ///```
 pub fn parse_heading(line: &str) -> (usize, String) {
-    let re: Regex = Regex::new(r"^#+\s*").unwrap();
     let line = line.trim();
-    let first = line.find(' ');
-    (
-        line.split_at(first.unwrap()).0.chars().into_iter().count(),
-        re.replace_all(line, "").to_string(),
-    )
+    let level = line.chars().take_while(|c| *c == '#').count();
+    (level, line[level..].trim().to_string())
 }
 
 ///```
@@ -82,27 +81,33 @@ pub fn is_ordered_list(line: &str) -> bool {
 
 ///```
 /// use saurus::transpiler::re;
-/// let (number, contents) = re::replace_ordered_list("7. Contents here!");
+/// let (number, contents) = re::replace_ordered_list("7. Contents here!").unwrap();
 /// assert_eq!(number, 7);
 /// assert_eq!(contents, "Contents here!");
+///
+/// assert_eq!(re::replace_ordered_list("c) Contents").unwrap(), (3, "Contents".to_string()));
+/// assert_eq!(re::replace_ordered_list("1a. Contents"), None);
 ///```
-pub fn replace_ordered_list(line: &str) -> (usize, String) {
-    let re = Regex::new(r"(\w*)\.\s*(.*)").unwrap();
-    let cap = re.captures(line).unwrap();
+pub fn replace_ordered_list(line: &str) -> Option<(usize, String)> {
+    let re = Regex::new(r"(\w*)[\.\)]\s*(.*)").unwrap();
+    let cap = re.captures(line)?;
 
-    let bullet = cap.get(1).unwrap().as_str().trim();
-    let parsed_bullet = bullet.parse::<usize>();
+    let bullet = cap.get(1)?.as_str().trim();
+    let contents = cap.get(2)?.as_str();
 
-    let contents = cap.get(2).unwrap().as_str();
-
-    if let Ok(bullet) = parsed_bullet {
-        return (bullet, contents.to_string())
+    if let Ok(bullet) = bullet.parse::<usize>() {
+        Some((bullet, contents.to_string()))
     } else {
         // TODO: Refactor at some point. Currently converts alphabetic lists to numeric
         // Potentially make it OrderedList(char) instead of usize and convert later
-        let bullet = bullet.chars().next().unwrap() as char;
-        let bullet = bullet.to_ascii_uppercase() as usize;
-        return (bullet - 64, contents.to_string())
+        let mut chars = bullet.chars();
+        match (chars.next(), chars.next()) {
+            (Some(bullet), None) if bullet.is_ascii_alphabetic() => Some((
+                bullet.to_ascii_uppercase() as usize - 64,
+                contents.to_string(),
+            )),
+            _ => None,
+        }
     }
 }
 
@@ -114,26 +119,44 @@ pub fn is_comment(line: &str) -> bool {
 ///```
 /// use saurus::transpiler::re;
 /// assert!(re::is_code_block(&"```python".to_string()));
+/// assert!(re::is_code_block("~~~"));
+/// assert!(!re::is_code_block("```inline``` code"));
 ///```
 pub fn is_code_block(line: &str) -> bool {
-    let re = Regex::new(r"^\s*```").unwrap();
-    re.is_match(line)
+    code_fence(line).is_some()
+}
+
+/// The character and length of the fence a line starts with. Like inline
+/// code, backtick fences cannot have backticks after them.
+///
+///```
+/// use saurus::transpiler::re;
+/// assert_eq!(re::code_fence("````rust"), Some(('`', 4)));
+/// assert_eq!(re::code_fence("  ~~~"), Some(('~', 3)));
+/// assert_eq!(re::code_fence("``"), None);
+///```
+pub fn code_fence(line: &str) -> Option<(char, usize)> {
+    let re = Regex::new(r"^\s*(`{3,}|~{3,})(.*)$").unwrap();
+    let captures = re.captures(line)?;
+    let fence = captures.get(1)?.as_str();
+    let c = fence.chars().next()?;
+    if c == '`' && captures.get(2)?.as_str().contains('`') {
+        return None;
+    }
+    Some((c, fence.len()))
 }
 
 ///```
 /// use saurus::transpiler::re;
 /// assert_eq!(re::replace_code_block(Some("```python")).unwrap(), "python".to_string());
+/// assert_eq!(re::replace_code_block(Some("~~~~ python")).unwrap(), "python".to_string());
+/// assert_eq!(re::replace_code_block(Some("````")), None);
 ///```
 pub fn replace_code_block(line: Option<&str>) -> Option<String> {
-    let re = Regex::new(r"\s*```(.+)").unwrap();
-    if let Some(line) = line {
-        let cap = re.captures(&line);
-        if let Some(cap) = cap {
-            let contents: &str = cap.get(1).unwrap().as_str();
-            return Some(contents.to_owned());
-        }
-    }
-    None
+    let line = line?;
+    let (_, length) = code_fence(line)?;
+    let info = line.trim_start()[length..].trim();
+    (!info.is_empty()).then(|| info.to_string())
 }
 
 /// ```
@@ -148,109 +171,15 @@ pub fn is_block_quote(line: &str) -> bool {
 
 /// ```
 /// use saurus::transpiler::re;
-/// assert_eq!(re::replace_block_quote(&"> this is my text"), "this is my text".to_string());
+/// assert_eq!(re::replace_block_quote(&"> this is my text"), Some("this is my text".to_string()));
+/// assert_eq!(re::replace_block_quote(&"not a quote"), None);
 /// ````
-pub fn replace_block_quote(line: &str) -> String {
+pub fn replace_block_quote(line: &str) -> Option<String> {
     let re = Regex::new(r"^>\s*(.*)").unwrap();
-    let cap = re.captures(line).unwrap();
-
-    let contents = cap.get(1).unwrap().as_str().trim();
-    contents.to_string()
-}
-
-///```
-/// use saurus::transpiler::re;
-/// assert_eq!(re::bold(&mut "**bold me**".to_string()), r"\textbf{bold me}".to_string());
-///```
-pub fn bold(line: &mut String) -> String {
-    //Option<String>
-    let re = Regex::new(r"\*\*([^\*]*)\*\*").unwrap();
-    re.replace_all(line, |caps: &Captures| format!("\\textbf{{{}}}", &caps[1]))
-        .to_string()
-}
-
-///```
-/// use saurus::transpiler::re;
-/// assert_eq!(re::italicize(&mut "*italicize me*".to_string()), r"\textit{italicize me}".to_string());
-/// assert_ne!(re::italicize(&mut "**italicize me**".to_string()), r"\textit{italicize me}*".to_string());
-///```
-pub fn italicize(line: &mut String) -> String {
-    //Option<String>
-    let re = Regex::new(r"\*([^\*]*)\*").unwrap();
-    re.replace_all(line, |caps: &Captures| format!("\\textit{{{}}}", &caps[1]))
-        .to_string()
-}
+    let cap = re.captures(line)?;
 
-///```
-/// use saurus::transpiler::re;
-/// assert_eq!(re::bold_italicize(&mut "***italicize me***".to_string()), r"\textbf{\textit{italicize me}}".to_string());
-///```
-pub fn bold_italicize(line: &mut String) -> String {
-    //Option<String>
-    let re = Regex::new(r"\*\*\*([^\*]*)\*\*\*").unwrap();
-    re.replace_all(line, |caps: &Captures| {
-        format!("\\textbf{{\\textit{{{}}}}}", &caps[1])
-    })
-    .to_string()
-}
-
-///```
-/// use saurus::transpiler::re;
-/// assert_eq!(re::links(&mut "[saurus](https://github.com/paytonward6/saurus)".to_string()), r"\href{https://github.com/paytonward6/saurus}{saurus}".to_string());
-/// assert_eq!(re::links(&mut "[indentfirst](https://ctan.org/pkg/indentfirst) text afterwards".to_string()), r"\href{https://ctan.org/pkg/indentfirst}{indentfirst} text afterwards".to_string());
-///```
-pub fn links(line: &mut String) -> String {
-    // \[.*\]([^\)]*) potentially fixes
-    let re = Regex::new(r"\[([a-zA-Z:][^\]]*)\]\((https://[^\)\(]*)\)").unwrap();
-    re.replace_all(line, |caps: &Captures| {
-        format!("\\href{{{}}}{{{}}}", &caps[2], &caps[1])
-    })
-    .to_string()
-}
-
-///```
-/// use saurus::transpiler::re;
-/// assert_eq!(re::inline_code(&mut "`let x = 2;`".to_string()), r"\verb|let x = 2;|".to_string());
-///```
-pub fn inline_code(line: &mut String) -> String {
-    //Option<String>
-    //let re = Regex::new(r"`([^`]*)`").unwrap();
-    let re = Regex::new(r"`([^`]+)`").unwrap();
-    re.replace_all(line, |caps: &Captures| format!("\\verb|{}|", &caps[1]))
-        .to_string()
-}
-
-///```
-/// use saurus::transpiler::re;
-/// assert_eq!(re::symbols(&mut "=>".to_string()), r"$\rightarrow$".to_string());
-/// assert_eq!(re::symbols(&mut "&rarr;".to_string()), r"$\rightarrow$".to_string());
-/// assert_eq!(re::symbols(&mut "$123".to_string()), r"\$123".to_string());
-/// assert_eq!(re::symbols(&mut r"$\frac{1}{2}$".to_string()), r"$\frac{1}{2}$".to_string());
-/// assert_eq!(re::symbols(&mut r"You & Me".to_string()), r"You \& Me".to_string());
-/// assert_eq!(re::symbols(&mut r"$Me \& You$".to_string()), r"$Me \& You$".to_string());
-///```
-pub fn symbols(line: &mut String) -> String {
-    //Option<String>
-    let arrows = Regex::new(r"=>|&rarr;").unwrap();
-    let dollar_signs = Regex::new(r"\$(\d+)").unwrap();
-    let ampersands = Regex::new(r"[^\\]&").unwrap();
-    *line = dollar_signs
-        .replace_all(line, |caps: &Captures| format!("\\${}", &caps[1]))
-        .to_string();
-    *line = ampersands.replace_all(line, r" \&").to_string();
-    arrows.replace_all(line, "$\\rightarrow$").to_string()
-}
-
-/// uses the "ulem" package
-/// ```
-/// use saurus::transpiler::re;
-/// assert_eq!(re::strike_out(&mut "~~strike this out~~".to_string()), r"\sout{strike this out}".to_string());
-/// ```
-pub fn strike_out(line: &mut String) -> String {
-    //Option<String>
-    let re = Regex::new(r"\~\~([^\~]*)\~\~").unwrap();
-    re.replace_all(line, |caps: &Captures| format!("\\sout{{{}}}", &caps[1]))
-        .to_string()
+    let contents = cap.get(1)?.as_str().trim();
+    Some(contents.to_string())
 }
 
 /// uses the "ulem" package
@@ -266,8 +195,8 @@ pub fn indent_level(line: &str) -> usize {
     } else {
         // Only works for 4 space indent
         let re = Regex::new(r"(\s*)\S").unwrap();
-        let cap = re.captures(line).unwrap();
-        let res = cap.get(1).map_or("", |m| m.as_str());
-        res.len() / 4
+        re.captures(line)
+            .and_then(|cap| cap.get(1))
+            .map_or(0, |m| m.as_str().len() / 4)
     }
 }
@@ -0,0 +1,101 @@
+use regex::{Captures, Regex};
+use serde::Serialize;
+
+use crate::transpiler::lexer::Span;
+
+/// Generated TeX lines and the Markdown lines they came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Mapping {
+    pub tex: Span,
+    pub markdown: Span,
+}
+
+/// Maps lines of a generated TeX document back to its Markdown source and
+/// vice versa.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SourceMap {
+    pub mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    pub fn push(&mut self, tex: Span, markdown: Span) {
+        self.mappings.push(Mapping { tex, markdown });
+    }
+
+    /// The Markdown lines that produced the given TeX line.
+    ///
+    /// ```
+    /// use saurus::transpiler::{lexer::Span, source_map::SourceMap};
+    ///
+    /// let mut map = SourceMap::default();
+    /// map.push(Span::new(20, 22), Span::line(3));
+    /// assert_eq!(map.markdown_lines(21), Some(Span::line(3)));
+    /// assert_eq!(map.markdown_lines(23), None);
+    /// ```
+    pub fn markdown_lines(&self, tex_line: usize) -> Option<Span> {
+        self.mappings
+            .iter()
+            .find(|mapping| mapping.tex.contains(tex_line))
+            .map(|mapping| mapping.markdown)
+    }
+
+    /// The TeX lines generated from the given Markdown line.
+    pub fn tex_lines(&self, markdown_line: usize) -> Option<Span> {
+        self.mappings
+            .iter()
+            .find(|mapping| mapping.markdown.contains(markdown_line))
+            .map(|mapping| mapping.tex)
+    }
+
+    /// Serializes the map as the JSON sidecar written next to the TeX output.
+    pub fn to_json(&self, markdown_file: &str, tex_file: &str) -> String {
+        let sidecar = serde_json::json!({
+            "markdown": markdown_file,
+            "tex": tex_file,
+            "mappings": self.mappings,
+        });
+        serde_json::to_string_pretty(&sidecar).expect("source map serializes to JSON")
+    }
+
+    /// Rewrites the `file:line:` locations (from `-file-line-error`) and the
+    /// `l.<line>` context lines in a LaTeX log to point at the Markdown source.
+    ///
+    /// ```
+    /// use saurus::transpiler::{lexer::Span, source_map::SourceMap};
+    ///
+    /// let mut map = SourceMap::default();
+    /// map.push(Span::line(214), Span::line(12));
+    /// let log = "./main.tex:214: Undefined control sequence.\nl.214 \\foo";
+    /// assert_eq!(
+    ///     map.translate_log(log, "main.tex", "notes.md"),
+    ///     "notes.md:12: Undefined control sequence.\nl.214 [notes.md:12] \\foo"
+    /// );
+    /// ```
+    pub fn translate_log(&self, log: &str, tex_file: &str, markdown_file: &str) -> String {
+        let location = Regex::new(&format!(r"(?m)^\S*{}:(\d+):", regex::escape(tex_file))).unwrap();
+        let context = Regex::new(r"(?m)^l\.(\d+)").unwrap();
+
+        let log = location.replace_all(log, |caps: &Captures| {
+            match caps[1]
+                .parse()
+                .ok()
+                .and_then(|line| self.markdown_lines(line))
+            {
+                Some(span) => format!("{}:{}:", markdown_file, span.start),
+                None => caps[0].to_string(),
+            }
+        });
+        context
+            .replace_all(&log, |caps: &Captures| {
+                match caps[1]
+                    .parse()
+                    .ok()
+                    .and_then(|line| self.markdown_lines(line))
+                {
+                    Some(span) => format!("{} [{}:{}]", &caps[0], markdown_file, span),
+                    None => caps[0].to_string(),
+                }
+            })
+            .to_string()
+    }
+}
@@ -0,0 +1,288 @@
+//! Styles for listings code blocks: the built-in `light`, `dark`, `print`
+//! and `solarized`, and any defined in the configuration.
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use saurus::transpiler::styles::{self, Frame, ListingStyle};
+//!
+//! let handout = ListingStyle {
+//!     base: Some("print".to_string()),
+//!     frame: Some(Frame::Lines),
+//!     ..ListingStyle::default()
+//! };
+//! let defined = HashMap::from([("handout".to_string(), handout)]);
+//! let style = styles::find("handout", &defined).unwrap();
+//! assert_eq!(style.frame, Some(Frame::Lines));
+//! assert_eq!(style.bold_keywords, Some(true));
+//! assert!(styles::definition("handout", &style).contains(r"\lstdefinestyle{handout}{"));
+//! ```
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// The style code blocks are listed in unless another is chosen
+pub const DEFAULT_STYLE: &str = "light";
+
+/// The names of the styles saurus defines
+pub const BUILTIN: [&str; 4] = ["light", "dark", "print", "solarized"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Font {
+    Monospace,
+    Sans,
+    Serif,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Size {
+    Tiny,
+    Scriptsize,
+    Footnotesize,
+    Small,
+    Normalsize,
+    Large,
+}
+
+/// How listings frames code, by its own names for frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Frame {
+    None,
+    Single,
+    Lines,
+    Leftline,
+    Topline,
+    Bottomline,
+    Shadowbox,
+}
+
+/// A listings style. Settings left unset come from `base`, if it has one,
+/// or else from listings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListingStyle {
+    /// The style this one changes, built in or not
+    pub base: Option<String>,
+    pub font: Option<Font>,
+    pub size: Option<Size>,
+    /// Colors, in hex such as `"F2F2EB"`
+    pub background: Option<String>,
+    pub text: Option<String>,
+    pub keywords: Option<String>,
+    pub identifiers: Option<String>,
+    pub comments: Option<String>,
+    pub strings: Option<String>,
+    pub bold_keywords: Option<bool>,
+    pub frame: Option<Frame>,
+    pub line_numbers: Option<bool>,
+    /// Wrap lines wider than the page
+    pub break_lines: Option<bool>,
+}
+
+impl ListingStyle {
+    /// The style's colors, along with what they color
+    pub fn colors(&self) -> [(&'static str, Option<&str>); 6] {
+        [
+            ("background", self.background.as_deref()),
+            ("text", self.text.as_deref()),
+            ("keywords", self.keywords.as_deref()),
+            ("identifiers", self.identifiers.as_deref()),
+            ("comments", self.comments.as_deref()),
+            ("strings", self.strings.as_deref()),
+        ]
+    }
+
+    /// `self`, with the settings it leaves unset taken from `base`
+    fn over(self, base: ListingStyle) -> ListingStyle {
+        ListingStyle {
+            base: None,
+            font: self.font.or(base.font),
+            size: self.size.or(base.size),
+            background: self.background.or(base.background),
+            text: self.text.or(base.text),
+            keywords: self.keywords.or(base.keywords),
+            identifiers: self.identifiers.or(base.identifiers),
+            comments: self.comments.or(base.comments),
+            strings: self.strings.or(base.strings),
+            bold_keywords: self.bold_keywords.or(base.bold_keywords),
+            frame: self.frame.or(base.frame),
+            line_numbers: self.line_numbers.or(base.line_numbers),
+            break_lines: self.break_lines.or(base.break_lines),
+        }
+    }
+}
+
+/// The built-in style called `name`
+pub fn builtin(name: &str) -> Option<ListingStyle> {
+    let color = |hex: &str| Some(hex.to_string());
+    let style = match name {
+        "light" => ListingStyle {
+            background: color("F2F2EB"),
+            keywords: color("0000FF"),
+            identifiers: color("800080"),
+            comments: color("009900"),
+            ..ListingStyle::default()
+        },
+        "dark" => ListingStyle {
+            font: Some(Font::Monospace),
+            background: color("282C34"),
+            text: color("ABB2BF"),
+            keywords: color("C678DD"),
+            identifiers: color("E5C07B"),
+            comments: color("7F848E"),
+            strings: color("98C379"),
+            ..ListingStyle::default()
+        },
+        // Prints the same in black and white
+        "print" => ListingStyle {
+            font: Some(Font::Monospace),
+            comments: color("707070"),
+            strings: color("404040"),
+            bold_keywords: Some(true),
+            frame: Some(Frame::Single),
+            ..ListingStyle::default()
+        },
+        "solarized" => ListingStyle {
+            font: Some(Font::Monospace),
+            background: color("FDF6E3"),
+            text: color("657B83"),
+            keywords: color("859900"),
+            identifiers: color("268BD2"),
+            comments: color("93A1A1"),
+            strings: color("2AA198"),
+            ..ListingStyle::default()
+        },
+        _ => return None,
+    };
+    Some(style)
+}
+
+/// The style called `name` among `defined` or the built-in ones, with the
+/// styles it is based on applied. `None` if there is no such style, or if
+/// it is based on itself.
+pub fn find(name: &str, defined: &HashMap<String, ListingStyle>) -> Option<ListingStyle> {
+    find_within(name, defined, defined.len())
+}
+
+fn find_within(
+    name: &str,
+    defined: &HashMap<String, ListingStyle>,
+    depth: usize,
+) -> Option<ListingStyle> {
+    let Some(style) = defined.get(name) else {
+        return builtin(name);
+    };
+    match &style.base {
+        // More bases than styles means that one is based on itself
+        Some(_) if depth == 0 => None,
+        Some(base) => Some(style.clone().over(find_within(base, defined, depth - 1)?)),
+        None => Some(style.clone()),
+    }
+}
+
+/// Whether `color` is a hex color such as `F2F2EB`
+pub fn is_color(color: &str) -> bool {
+    color.len() == 6 && color.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// The colors and `\lstdefinestyle` of the style `name`.
+pub fn definition(name: &str, style: &ListingStyle) -> String {
+    let mut definition = String::new();
+    for (role, color) in style.colors() {
+        if let Some(color) = color {
+            definition.push_str(&format!(
+                "\n    \\definecolor{{{}-{}}}{{HTML}}{{{}}}",
+                name, role, color
+            ));
+        }
+    }
+
+    let color = |role: &str| format!("\\color{{{}-{}}}", name, role);
+    let mut settings = Vec::new();
+    let mut basic = String::new();
+    if let Some(font) = style.font {
+        basic.push_str(match font {
+            Font::Monospace => "\\ttfamily",
+            Font::Sans => "\\sffamily",
+            Font::Serif => "\\rmfamily",
+        });
+    }
+    if let Some(size) = style.size {
+        basic.push_str(match size {
+            Size::Tiny => "\\tiny",
+            Size::Scriptsize => "\\scriptsize",
+            Size::Footnotesize => "\\footnotesize",
+            Size::Small => "\\small",
+            Size::Normalsize => "\\normalsize",
+            Size::Large => "\\large",
+        });
+    }
+    if style.text.is_some() {
+        basic.push_str(&color("text"));
+    }
+    if !basic.is_empty() {
+        settings.push(format!("basicstyle={}", basic));
+    }
+    if style.background.is_some() {
+        settings.push(format!("backgroundcolor={}", color("background")));
+    }
+    let mut keywords = String::new();
+    if style.bold_keywords == Some(true) {
+        keywords.push_str("\\bfseries");
+    }
+    if style.keywords.is_some() {
+        keywords.push_str(&color("keywords"));
+    }
+    if !keywords.is_empty() {
+        settings.push(format!("keywordstyle={}", keywords));
+    }
+    for (role, option, value) in [
+        ("identifiers", "identifierstyle", &style.identifiers),
+        ("comments", "commentstyle", &style.comments),
+        ("strings", "stringstyle", &style.strings),
+    ] {
+        if value.is_some() {
+            settings.push(format!("{}={}", option, color(role)));
+        }
+    }
+    settings.extend(
+        [
+            "showspaces=false",
+            "showstringspaces=false",
+            "keepspaces=true",
+            "extendedchars=true",
+        ]
+        .map(str::to_string),
+    );
+    if let Some(frame) = style.frame {
+        let frame = match frame {
+            Frame::None => "none",
+            Frame::Single => "single",
+            Frame::Lines => "lines",
+            Frame::Leftline => "leftline",
+            Frame::Topline => "topline",
+            Frame::Bottomline => "bottomline",
+            Frame::Shadowbox => "shadowbox",
+        };
+        settings.push(format!("frame={}", frame));
+    }
+    if let Some(line_numbers) = style.line_numbers {
+        settings.push(format!(
+            "numbers={}",
+            if line_numbers { "left" } else { "none" }
+        ));
+    }
+    if let Some(break_lines) = style.break_lines {
+        settings.push(format!("breaklines={}", break_lines));
+    }
+
+    definition.push_str(&format!("\n    \\lstdefinestyle{{{}}}{{\n", name));
+    for setting in settings {
+        definition.push_str(&format!("        {},\n", setting));
+    }
+    definition.push_str("    }");
+    definition
+}
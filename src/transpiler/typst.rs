@@ -0,0 +1,405 @@
+//! [Typst](https://typst.app) output.
+
+use crate::transpiler::ast::{Block, CodeBlock, Heading, Inline, List, ListKind, Quote, RawLatex};
+use crate::transpiler::backend::{self, Backend, Context, Fragment, Resource, Resources};
+use crate::transpiler::generator::indent_lines;
+use crate::transpiler::lexer::Span;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Typst;
+
+impl Backend for Typst {
+    fn prologue(&mut self, cx: &mut Context) -> String {
+        format!("{}\n", preamble(&cx.resources))
+    }
+
+    fn epilogue(&mut self, _cx: &mut Context) -> String {
+        String::new()
+    }
+
+    fn comment(&mut self, text: &str) -> String {
+        format!("// {}", text)
+    }
+
+    fn heading(&mut self, heading: &Heading, cx: &mut Context) -> Vec<Fragment> {
+        let text = format!(
+            "{} {}",
+            "=".repeat(heading.level.max(1)),
+            self.inlines(&heading.content, cx)
+        );
+        vec![Fragment::new(text, heading.span)]
+    }
+
+    fn list(&mut self, list: &List, cx: &mut Context) -> Vec<Fragment> {
+        let indent = "  ".repeat(cx.depth);
+        let mut fragments = Vec::new();
+        for (index, item) in list.items.iter().enumerate() {
+            let marker = match list.kind {
+                ListKind::Unordered => "-".to_string(),
+                // Unnumbered items continue counting from the explicit first number
+                ListKind::Ordered { start } if index == 0 && start != 1 => format!("{}.", start),
+                ListKind::Ordered { .. } => "+".to_string(),
+            };
+            let line = self.inlines(&item.content, cx);
+            fragments.push(Fragment::new(
+                format!("{}{} {}", indent, marker, line),
+                Span::line(item.span.start),
+            ));
+            cx.depth += 1;
+            for child in item.children.iter() {
+                let child_indent = "  ".repeat(cx.depth);
+                for mut fragment in self.block(child, cx) {
+                    // Nested lists indent themselves, anything else must be
+                    // indented to stay part of the item
+                    if !matches!(child, Block::List(_)) {
+                        fragment.text = indent_lines(&fragment.text, &child_indent);
+                    }
+                    fragments.push(fragment);
+                }
+            }
+            cx.depth -= 1;
+        }
+        fragments
+    }
+
+    fn quote(&mut self, quote: &Quote, cx: &mut Context) -> Vec<Fragment> {
+        let depth = cx.depth;
+        cx.depth = 0;
+        let mut body = Vec::new();
+        for block in quote.blocks.iter() {
+            let text = self
+                .block(block, cx)
+                .into_iter()
+                .map(|fragment| fragment.text)
+                .collect::<Vec<_>>()
+                .join("\n");
+            body.push(indent_lines(&text, "  "));
+        }
+        cx.depth = depth;
+        let text = format!("#quote(block: true)[\n{}\n]", body.join("\n\n"));
+        vec![Fragment::new(text, quote.span)]
+    }
+
+    fn code_block(&mut self, code_block: &CodeBlock, _cx: &mut Context) -> Vec<Fragment> {
+        let fence = "`".repeat(longest_run(&code_block.code, '`').max(2) + 1);
+        let language = code_block.language.as_deref().unwrap_or_default();
+        let mut text = if code_block.code.is_empty() {
+            format!("{}{}\n{}", fence, language, fence)
+        } else {
+            format!("{}{}\n{}\n{}", fence, language, code_block.code, fence)
+        };
+        let attributes = &code_block.attributes;
+        if attributes.caption.is_some() || attributes.label.is_some() {
+            let caption = attributes
+                .caption
+                .as_deref()
+                .map_or(String::new(), |caption| {
+                    format!("caption: [{}], ", escape(caption))
+                });
+            text = format!("#figure({}kind: raw)[\n{}\n]", caption, text);
+            if let Some(label) = &attributes.label {
+                text.push_str(&format!(" <{}>", label));
+            }
+        }
+        vec![Fragment::new(text, code_block.span)]
+    }
+
+    fn text(&mut self, text: &str, _cx: &mut Context) -> String {
+        escape(text)
+    }
+
+    fn bold(&mut self, content: &[Inline], cx: &mut Context) -> String {
+        format!("#strong[{}]", self.inlines(content, cx))
+    }
+
+    fn italic(&mut self, content: &[Inline], cx: &mut Context) -> String {
+        format!("#emph[{}]", self.inlines(content, cx))
+    }
+
+    fn strike(&mut self, content: &[Inline], cx: &mut Context) -> String {
+        format!("#strike[{}]", self.inlines(content, cx))
+    }
+
+    fn code(&mut self, code: &str, _cx: &mut Context) -> String {
+        if code.contains('`') {
+            format!("#raw({})", string_literal(code))
+        } else {
+            format!("`{}`", code)
+        }
+    }
+
+    fn link(&mut self, content: &[Inline], url: &str, cx: &mut Context) -> String {
+        format!(
+            "#link({})[{}]",
+            string_literal(url),
+            self.inlines(content, cx)
+        )
+    }
+
+    fn math(&mut self, math: &str, _cx: &mut Context) -> String {
+        format!("${}$", math_from_latex(math))
+    }
+
+    fn raw_block(&mut self, raw: &RawLatex, cx: &mut Context) -> Vec<Fragment> {
+        backend::drop_raw_latex(raw.span.start, cx);
+        Vec::new()
+    }
+
+    fn raw_inline(&mut self, _latex: &str, cx: &mut Context) -> String {
+        backend::drop_raw_latex(0, cx);
+        String::new()
+    }
+
+    fn line_break(&mut self, _cx: &mut Context) -> String {
+        "\\\n".to_string()
+    }
+}
+
+/// Page setup mirroring the packages of the LaTeX preamble.
+pub fn preamble(resources: &Resources) -> String {
+    let mut preamble = String::from(
+        "#set page(paper: \"us-letter\", margin: 1in)\n\
+         #set par(first-line-indent: 1.5em)\n\
+         #set heading(numbering: none)\n",
+    );
+    if resources.contains(&Resource::Links) {
+        preamble.push_str("#show link: set text(fill: blue)\n");
+    }
+    if resources.contains(&Resource::CodeBlocks) {
+        preamble.push_str(
+            "#show raw.where(block: true): block.with(\n\
+             \x20   fill: rgb(95%, 95%, 92%),\n\
+             \x20   inset: 8pt,\n\
+             \x20   width: 100%,\n\
+             )\n",
+        );
+    }
+    preamble
+}
+
+/// Escapes the characters that Typst markup would otherwise interpret.
+///
+/// ```
+/// use saurus::transpiler::typst;
+/// assert_eq!(typst::escape("a*b_c #1 <x> @y"), r"a\*b\_c \#1 \<x> \@y");
+/// assert_eq!(typst::escape("- not a list"), r"\- not a list");
+/// assert_eq!(typst::escape("a // b /* c */"), r"a \// b \/\* c \*/");
+/// ```
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let starts_line = escaped.is_empty() && matches!(c, '=' | '-' | '+' | '/');
+        let starts_comment = c == '/' && matches!(chars.peek(), Some('/' | '*'));
+        if starts_line
+            || starts_comment
+            || matches!(
+                c,
+                '\\' | '#' | '*' | '_' | '`' | '$' | '<' | '@' | '[' | ']' | '~'
+            )
+        {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn string_literal(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn longest_run(text: &str, run: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in text.chars() {
+        current = if c == run { current + 1 } else { 0 };
+        longest = longest.max(current);
+    }
+    longest
+}
+
+/// LaTeX math commands whose Typst name differs
+const MATH_SYMBOLS: [(&str, &str); 28] = [
+    ("cdot", "dot"),
+    ("pm", "plus.minus"),
+    ("mp", "minus.plus"),
+    ("leq", "<="),
+    ("le", "<="),
+    ("geq", ">="),
+    ("ge", ">="),
+    ("neq", "!="),
+    ("ne", "!="),
+    ("infty", "infinity"),
+    ("rightarrow", "arrow.r"),
+    ("to", "arrow.r"),
+    ("leftarrow", "arrow.l"),
+    ("Rightarrow", "arrow.r.double"),
+    ("Leftarrow", "arrow.l.double"),
+    ("leftrightarrow", "arrow.l.r"),
+    ("Leftrightarrow", "arrow.l.r.double"),
+    ("implies", "arrow.r.double.long"),
+    ("iff", "arrow.l.r.double.long"),
+    ("notin", "in.not"),
+    ("subseteq", "subset.eq"),
+    ("cup", "union"),
+    ("cap", "sect"),
+    ("partial", "diff"),
+    ("int", "integral"),
+    ("prod", "product"),
+    ("ldots", "dots.h"),
+    ("cdots", "dots.c"),
+];
+
+/// Best-effort translation of LaTeX math into Typst math. Commands without
+/// a known Typst equivalent keep their name, which matches Typst's own for
+/// Greek letters and most operators.
+///
+/// ```
+/// use saurus::transpiler::typst;
+/// assert_eq!(typst::math_from_latex(r"\frac{1}{2}"), "frac(1, 2)");
+/// assert_eq!(typst::math_from_latex(r"x^{10} + \alpha_i"), "x^(10) + alpha_i");
+/// assert_eq!(typst::math_from_latex(r"ab \leq \sqrt{c}"), "a b <= sqrt(c)");
+/// ```
+pub fn math_from_latex(math: &str) -> String {
+    let chars: Vec<char> = math.chars().collect();
+    let mut index = 0;
+    convert_math(&chars, &mut index, false)
+}
+
+fn convert_math(chars: &[char], index: &mut usize, in_group: bool) -> String {
+    let mut atoms: Vec<String> = Vec::new();
+    while *index < chars.len() {
+        let c = chars[*index];
+        *index += 1;
+        match c {
+            '}' if in_group => break,
+            '{' => atoms.push(convert_math(chars, index, true)),
+            '^' | '_' => {
+                let argument = math_argument(chars, index);
+                let attached = if argument.chars().count() == 1 {
+                    format!("{}{}", c, argument)
+                } else {
+                    format!("{}({})", c, argument)
+                };
+                // Attachments must touch what they attach to
+                match atoms.last_mut() {
+                    Some(last) => last.push_str(&attached),
+                    None => atoms.push(attached),
+                }
+            }
+            '\\' => {
+                let name: String = chars[*index..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_alphabetic())
+                    .collect();
+                *index += name.len();
+                if name.is_empty() {
+                    // An escaped symbol such as `\{` or `\,`
+                    if let Some(symbol) = chars.get(*index) {
+                        *index += 1;
+                        if !matches!(symbol, ',' | ';' | '!' | ' ') {
+                            atoms.push(format!("\\{}", symbol));
+                        }
+                    }
+                    continue;
+                }
+                let atom = match name.as_str() {
+                    "frac" | "dfrac" | "tfrac" => {
+                        let numerator = math_argument(chars, index);
+                        let denominator = math_argument(chars, index);
+                        format!("frac({}, {})", numerator, denominator)
+                    }
+                    "sqrt" => format!("sqrt({})", math_argument(chars, index)),
+                    "text" | "mathrm" | "operatorname" => {
+                        let text: String = raw_argument(chars, index);
+                        format!("\"{}\"", text.replace('"', "\\\""))
+                    }
+                    "mathbf" => format!("bold({})", math_argument(chars, index)),
+                    "mathit" => format!("italic({})", math_argument(chars, index)),
+                    "mathbb" => format!("bb({})", math_argument(chars, index)),
+                    "left" | "right" => continue,
+                    _ => MATH_SYMBOLS
+                        .iter()
+                        .find(|(latex, _)| *latex == name)
+                        .map_or(name.clone(), |(_, typst)| typst.to_string()),
+                };
+                atoms.push(atom);
+            }
+            c if c.is_whitespace() => {}
+            c if c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                while let Some(digit) = chars
+                    .get(*index)
+                    .filter(|c| c.is_ascii_digit() || **c == '.')
+                {
+                    number.push(*digit);
+                    *index += 1;
+                }
+                atoms.push(number);
+            }
+            // Single letters, so that `xy` is not read as one identifier
+            c => atoms.push(c.to_string()),
+        }
+    }
+    atoms.join(" ")
+}
+
+/// The next `{group}` or single character, converted
+fn math_argument(chars: &[char], index: &mut usize) -> String {
+    while chars.get(*index).is_some_and(|c| c.is_whitespace()) {
+        *index += 1;
+    }
+    match chars.get(*index) {
+        Some('{') => {
+            *index += 1;
+            convert_math(chars, index, true)
+        }
+        Some('\\') => {
+            let start = *index;
+            *index += 1;
+            let length = chars[*index..]
+                .iter()
+                .take_while(|c| c.is_ascii_alphabetic())
+                .count()
+                .max(1);
+            *index += length;
+            let command: Vec<char> = chars[start..*index].to_vec();
+            convert_math(&command, &mut 0, false)
+        }
+        Some(c) => {
+            *index += 1;
+            c.to_string()
+        }
+        None => String::new(),
+    }
+}
+
+/// The next `{group}` taken verbatim
+fn raw_argument(chars: &[char], index: &mut usize) -> String {
+    if chars.get(*index) != Some(&'{') {
+        return String::new();
+    }
+    let mut depth = 0;
+    let mut text = String::new();
+    while let Some(c) = chars.get(*index) {
+        *index += 1;
+        match c {
+            '{' => {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            }
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        text.push(*c);
+    }
+    text
+}
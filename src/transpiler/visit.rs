@@ -0,0 +1,215 @@
+//! Traversal of the document tree.
+//!
+//! Every `visit_*` method defaults to calling the matching `walk_*` function,
+//! which visits the node's children. Override a method to act on a node, and
+//! call the `walk_*` function from it to keep descending.
+//!
+//! ```
+//! use saurus::transpiler::ast::Inline;
+//! use saurus::transpiler::visit::{self, VisitorMut};
+//! use saurus::transpiler::{parse, Options};
+//!
+//! struct RewriteUrls;
+//!
+//! impl VisitorMut for RewriteUrls {
+//!     fn visit_inline_mut(&mut self, inline: &mut Inline) {
+//!         if let Inline::Link { url, .. } = inline {
+//!             *url = url.replace("http://wiki/", "https://wiki.example.com/");
+//!         }
+//!         visit::walk_inline_mut(self, inline);
+//!     }
+//! }
+//!
+//! let mut document = parse("See [the wiki](http://wiki/page)", &mut Vec::new());
+//! RewriteUrls.visit_document_mut(&mut document);
+//! let (tex, _) = saurus::transpiler::generate(&document, &Options::default(), &mut Vec::new());
+//! assert!(tex.contains(r"\href{https://wiki.example.com/page}{the wiki}"));
+//! ```
+
+use crate::transpiler::ast::{
+    Block, CodeBlock, Document, Heading, Inline, Item, List, Paragraph, Quote,
+};
+
+pub trait Visitor {
+    fn visit_document(&mut self, document: &Document) {
+        walk_document(self, document)
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block)
+    }
+
+    fn visit_heading(&mut self, heading: &Heading) {
+        walk_inlines(self, &heading.content)
+    }
+
+    fn visit_paragraph(&mut self, paragraph: &Paragraph) {
+        walk_inlines(self, &paragraph.content)
+    }
+
+    fn visit_list(&mut self, list: &List) {
+        walk_list(self, list)
+    }
+
+    fn visit_item(&mut self, item: &Item) {
+        walk_item(self, item)
+    }
+
+    fn visit_quote(&mut self, quote: &Quote) {
+        walk_blocks(self, &quote.blocks)
+    }
+
+    fn visit_code_block(&mut self, _code_block: &CodeBlock) {}
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        walk_inline(self, inline)
+    }
+}
+
+pub fn walk_document<V: Visitor + ?Sized>(visitor: &mut V, document: &Document) {
+    walk_blocks(visitor, &document.blocks)
+}
+
+pub fn walk_blocks<V: Visitor + ?Sized>(visitor: &mut V, blocks: &[Block]) {
+    for block in blocks {
+        visitor.visit_block(block);
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &Block) {
+    match block {
+        Block::Heading(heading) => visitor.visit_heading(heading),
+        Block::Paragraph(paragraph) => visitor.visit_paragraph(paragraph),
+        Block::List(list) => visitor.visit_list(list),
+        Block::Quote(quote) => visitor.visit_quote(quote),
+        Block::CodeBlock(code_block) => visitor.visit_code_block(code_block),
+        Block::RawLatex(_) => {}
+    }
+}
+
+pub fn walk_list<V: Visitor + ?Sized>(visitor: &mut V, list: &List) {
+    for item in list.items.iter() {
+        visitor.visit_item(item);
+    }
+}
+
+pub fn walk_item<V: Visitor + ?Sized>(visitor: &mut V, item: &Item) {
+    walk_inlines(visitor, &item.content);
+    walk_blocks(visitor, &item.children);
+}
+
+pub fn walk_inlines<V: Visitor + ?Sized>(visitor: &mut V, inlines: &[Inline]) {
+    for inline in inlines {
+        visitor.visit_inline(inline);
+    }
+}
+
+pub fn walk_inline<V: Visitor + ?Sized>(visitor: &mut V, inline: &Inline) {
+    match inline {
+        Inline::Bold(content)
+        | Inline::Italic(content)
+        | Inline::Strike(content)
+        | Inline::Link { content, .. } => walk_inlines(visitor, content),
+        Inline::Text(_)
+        | Inline::Code(_)
+        | Inline::Math(_)
+        | Inline::RawLatex(_)
+        | Inline::SoftBreak
+        | Inline::LineBreak => {}
+    }
+}
+
+/// Like [`Visitor`], but able to change the tree. Blocks are visited through
+/// their containing `Vec` so they can also be removed or inserted.
+pub trait VisitorMut {
+    fn visit_document_mut(&mut self, document: &mut Document) {
+        walk_document_mut(self, document)
+    }
+
+    fn visit_blocks_mut(&mut self, blocks: &mut Vec<Block>) {
+        walk_blocks_mut(self, blocks)
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block)
+    }
+
+    fn visit_heading_mut(&mut self, heading: &mut Heading) {
+        walk_inlines_mut(self, &mut heading.content)
+    }
+
+    fn visit_paragraph_mut(&mut self, paragraph: &mut Paragraph) {
+        walk_inlines_mut(self, &mut paragraph.content)
+    }
+
+    fn visit_list_mut(&mut self, list: &mut List) {
+        walk_list_mut(self, list)
+    }
+
+    fn visit_item_mut(&mut self, item: &mut Item) {
+        walk_item_mut(self, item)
+    }
+
+    fn visit_quote_mut(&mut self, quote: &mut Quote) {
+        self.visit_blocks_mut(&mut quote.blocks)
+    }
+
+    fn visit_code_block_mut(&mut self, _code_block: &mut CodeBlock) {}
+
+    fn visit_inline_mut(&mut self, inline: &mut Inline) {
+        walk_inline_mut(self, inline)
+    }
+}
+
+pub fn walk_document_mut<V: VisitorMut + ?Sized>(visitor: &mut V, document: &mut Document) {
+    visitor.visit_blocks_mut(&mut document.blocks)
+}
+
+pub fn walk_blocks_mut<V: VisitorMut + ?Sized>(visitor: &mut V, blocks: &mut [Block]) {
+    for block in blocks.iter_mut() {
+        visitor.visit_block_mut(block);
+    }
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(visitor: &mut V, block: &mut Block) {
+    match block {
+        Block::Heading(heading) => visitor.visit_heading_mut(heading),
+        Block::Paragraph(paragraph) => visitor.visit_paragraph_mut(paragraph),
+        Block::List(list) => visitor.visit_list_mut(list),
+        Block::Quote(quote) => visitor.visit_quote_mut(quote),
+        Block::CodeBlock(code_block) => visitor.visit_code_block_mut(code_block),
+        Block::RawLatex(_) => {}
+    }
+}
+
+pub fn walk_list_mut<V: VisitorMut + ?Sized>(visitor: &mut V, list: &mut List) {
+    for item in list.items.iter_mut() {
+        visitor.visit_item_mut(item);
+    }
+}
+
+pub fn walk_item_mut<V: VisitorMut + ?Sized>(visitor: &mut V, item: &mut Item) {
+    walk_inlines_mut(visitor, &mut item.content);
+    visitor.visit_blocks_mut(&mut item.children);
+}
+
+pub fn walk_inlines_mut<V: VisitorMut + ?Sized>(visitor: &mut V, inlines: &mut [Inline]) {
+    for inline in inlines.iter_mut() {
+        visitor.visit_inline_mut(inline);
+    }
+}
+
+pub fn walk_inline_mut<V: VisitorMut + ?Sized>(visitor: &mut V, inline: &mut Inline) {
+    match inline {
+        Inline::Bold(content)
+        | Inline::Italic(content)
+        | Inline::Strike(content)
+        | Inline::Link { content, .. } => walk_inlines_mut(visitor, content),
+        Inline::Text(_)
+        | Inline::Code(_)
+        | Inline::Math(_)
+        | Inline::RawLatex(_)
+        | Inline::SoftBreak
+        | Inline::LineBreak => {}
+    }
+}